@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::fs;
 use std::io::{stdout, Write};
 use std::path::Path;
@@ -13,6 +12,7 @@ use crossterm::cursor;
 
 use threadpool::ThreadPool;
 
+use crate::task::Task;
 use crate::util::*;
 
 mod client_wrapper;
@@ -41,7 +41,7 @@ pub struct ApproxArgs {
     remote: bool,
 }
 
-pub fn approx(args: ApproxArgs, _params: &HashMap<String, String>) {
+pub fn approx(args: ApproxArgs, _task: &Task) {
     let config = read_config();
 
     let tests_info: Arc<Mutex<Vec<TestInfo>>> = Arc::new(Mutex::new(Vec::new()));
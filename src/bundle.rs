@@ -0,0 +1,481 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use crate::task::Task;
+use crate::{get_default_file_extension, read_settings, DEFAULT_FILE_NAME};
+
+#[derive(Parser)]
+pub struct BundleArgs {
+    /// Source file, "main" of the task by default
+    filename: Option<String>,
+
+    /// Output file, "bundle.[extension]" by default, "-" for stdout
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Code under "#ifdef" of these macros is removed, "#else" branches are kept (C++ only)
+    #[arg(long, default_values_t = ["HOME".to_string(), "HOUSE".to_string()])]
+    strip: Vec<String>,
+}
+
+pub fn bundle(args: BundleArgs, task: &Task) {
+    let filename = args.filename.clone().or(task.main.clone()).unwrap_or_else(|| {
+        format!(
+            "{}.{}",
+            DEFAULT_FILE_NAME,
+            task.lang.clone().unwrap_or_else(get_default_file_extension)
+        )
+    });
+    let extension = Path::new(&filename)
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let libs_path = read_settings().and_then(|x| x.config.get(x.active_profile())?.libs_path.clone());
+
+    let result = match extension.as_str() {
+        "cpp" | "cc" | "h" | "hpp" => {
+            let mut bundler = CppBundler {
+                libs_path: libs_path.map(PathBuf::from),
+                strip: args.strip.iter().cloned().collect(),
+                included: HashSet::new(),
+                res: String::new(),
+            };
+            bundler.add_file(Path::new(&filename)).map(|_| bundler.res)
+        }
+        "rs" => bundle_rust(Path::new(&filename)),
+        _ => Err(format!("can't bundle \".{}\" files", extension)),
+    };
+    let result = match result {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Can't bundle {}: {}", filename, e);
+            std::process::exit(1);
+        }
+    };
+
+    let output = args.output.unwrap_or(format!("bundle.{}", extension));
+    if output == "-" {
+        print!("{}", result);
+    } else {
+        fs::write(&output, result).unwrap();
+        eprintln!("Bundled {} into {}", filename, output);
+    }
+}
+
+/// What to do with the lines of an open "#if"
+enum Condition {
+    /// Usual condition, directives are kept for the compiler
+    Keep,
+    /// "#ifdef" of a stripped macro: whether the current branch is kept and whether some branch was kept
+    Strip(bool, bool),
+}
+
+struct CppBundler {
+    libs_path: Option<PathBuf>,
+    strip: HashSet<String>,
+    included: HashSet<PathBuf>,
+    res: String,
+}
+
+impl CppBundler {
+    /// Appends the file with local includes expanded, each file is included only once
+    fn add_file(&mut self, path: &Path) -> Result<(), String> {
+        let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let mut stack: Vec<Condition> = Vec::new();
+        let active = |stack: &Vec<Condition>| stack.iter().all(|x| !matches!(x, Condition::Strip(false, _)));
+
+        for (i, line) in data.lines().enumerate() {
+            let (directive, arg) = match line.trim().strip_prefix('#') {
+                Some(rest) => {
+                    let rest = rest.trim_start();
+                    let end = rest.find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len());
+                    (&rest[..end], rest[end..].trim())
+                }
+                None => ("", ""),
+            };
+            let macro_name = arg.split("//").next().unwrap_or_default().trim();
+            match directive {
+                "ifdef" | "ifndef" if self.strip.contains(macro_name) => {
+                    // the macros are not defined on the judge
+                    let kept = directive == "ifndef";
+                    stack.push(Condition::Strip(kept, kept));
+                }
+                "if" | "ifdef" | "ifndef" => {
+                    if active(&stack) {
+                        self.push_line(line);
+                    }
+                    stack.push(Condition::Keep);
+                }
+                "elif" | "else" | "endif" => {
+                    let top = stack.pop().ok_or(format!(
+                        "{}:{}: \"#{}\" without \"#if\"",
+                        path.display(),
+                        i + 1,
+                        directive
+                    ))?;
+                    let parent_active = active(&stack);
+                    match (top, directive) {
+                        (Condition::Keep, _) => {
+                            if parent_active {
+                                self.push_line(line);
+                            }
+                            if directive != "endif" {
+                                stack.push(Condition::Keep);
+                            }
+                        }
+                        (Condition::Strip(_, true), "elif" | "else") => stack.push(Condition::Strip(false, true)),
+                        (Condition::Strip(_, false), "else") => stack.push(Condition::Strip(true, true)),
+                        (Condition::Strip(_, false), "elif") => {
+                            // the stripped branch is never taken, so "#elif" becomes the first one
+                            if parent_active {
+                                self.push_line(&format!("#if {}", arg));
+                            }
+                            stack.push(Condition::Keep);
+                        }
+                        _ => {}
+                    }
+                }
+                _ if !active(&stack) => {}
+                "pragma" if arg == "once" => {}
+                "include" if arg.starts_with('"') => {
+                    match self.find_include(&dir, arg.split('"').nth(1).unwrap_or_default()) {
+                        Some(include) => {
+                            let canonical = include.canonicalize().unwrap_or(include.clone());
+                            if self.included.insert(canonical) {
+                                self.add_file(&include)?;
+                            }
+                        }
+                        None => self.push_line(line),
+                    }
+                }
+                _ => self.push_line(line),
+            }
+        }
+        if !stack.is_empty() {
+            return Err(format!(
+                "{}: {} \"#if\" without \"#endif\"",
+                path.display(),
+                stack.len()
+            ));
+        }
+        Ok(())
+    }
+
+    fn push_line(&mut self, line: &str) {
+        self.res += line;
+        self.res.push('\n');
+    }
+
+    /// Included file relative to the including one, then to "libs_path"
+    fn find_include(&self, dir: &Path, name: &str) -> Option<PathBuf> {
+        [Some(dir), self.libs_path.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(|x| x.join(name))
+            .find(|x| x.is_file())
+    }
+}
+
+/// Solution with the used path dependencies from Cargo.toml (the rlib crates) inlined as modules
+fn bundle_rust(path: &Path) -> Result<String, String> {
+    let solution = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let crates = path_dependencies(&find_manifest(path));
+
+    let mut res = rewrite_paths(&remove_extern_crates(&solution, &crates), None, &crates);
+    let mut used: Vec<&String> = crates.keys().filter(|x| contains_word(&solution, x)).collect();
+    let mut i = 0;
+    while i < used.len() {
+        let name = used[i];
+        let lib = inline_module(&crates[name].join("src/lib.rs"), true)?;
+        for other in crates.keys() {
+            if !used.contains(&other) && contains_word(&lib, other) {
+                used.push(other);
+            }
+        }
+        let lib = rewrite_paths(&remove_extern_crates(&lib, &crates), Some(name), &crates);
+        res += &format!(
+            "\n#[allow(dead_code, unused)]\n#[macro_use]\npub mod {} {{\n{}}}\n",
+            name, lib
+        );
+        i += 1;
+    }
+    Ok(res)
+}
+
+/// Cargo.toml of the package with the source file
+pub fn find_manifest(path: &Path) -> PathBuf {
+    path.ancestors()
+        .skip(1)
+        .map(|x| x.join("Cargo.toml"))
+        .find(|x| x.is_file())
+        .unwrap_or(PathBuf::from("Cargo.toml"))
+}
+
+/// Crates with "path" in [dependencies] of Cargo.toml, as written by "init_rust_directory".
+/// Relative paths are resolved against the folder of Cargo.toml
+pub fn path_dependencies(manifest: &Path) -> BTreeMap<String, PathBuf> {
+    let mut res = BTreeMap::new();
+    let data = fs::read_to_string(manifest).unwrap_or_default();
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let mut dependencies = false;
+    for line in data.lines().map(|x| x.trim()) {
+        if line.starts_with('[') {
+            dependencies = line == "[dependencies]";
+            continue;
+        }
+        let (name, value) = match line.split_once('=') {
+            Some(x) if dependencies => x,
+            _ => continue,
+        };
+        if let Some(path) = path_value(value).filter(|x| !x.is_empty()) {
+            res.insert(name.trim().trim_matches('"').replace('-', "_"), dir.join(path));
+        }
+    }
+    res
+}
+
+/// Value of the "path" key (quoted or not) in an inline table like "{ path = "../x" }"
+fn path_value(table: &str) -> Option<String> {
+    table.match_indices("path").find_map(|(i, _)| {
+        let is_key = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+        if table[..i].chars().last().is_some_and(is_key) {
+            return None;
+        }
+        let rest = table[i + "path".len()..]
+            .strip_prefix('"')
+            .unwrap_or(&table[i + "path".len()..]);
+        let rest = rest.trim_start().strip_prefix('=')?.trim_start().strip_prefix('"')?;
+        // basic string with "\\" and "\"" escapes, as written by "dependency_line"
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(value),
+                '\\' => value.push(chars.next()?),
+                _ => value.push(c),
+            }
+        }
+        None
+    })
+}
+
+/// Contents of the file with "mod x;" replaced by the contents of the module file
+fn inline_module(path: &Path, is_root: bool) -> Result<String, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let dir = if is_root || path.file_name().is_some_and(|x| x == "mod.rs") {
+        path.parent().unwrap_or(Path::new(".")).to_path_buf()
+    } else {
+        path.with_extension("")
+    };
+    let mut res = String::new();
+    for line in data.lines() {
+        let declaration = line.trim().strip_suffix(';').and_then(|x| {
+            let (visibility, name) = x.rsplit_once("mod ")?;
+            let visibility = visibility.trim();
+            let valid = visibility.is_empty() || visibility.starts_with("pub");
+            let name = name.trim();
+            (valid && name.chars().all(|c| c.is_alphanumeric() || c == '_')).then_some((visibility, name))
+        });
+        match declaration {
+            Some((visibility, name)) => {
+                let file = [dir.join(format!("{}.rs", name)), dir.join(name).join("mod.rs")]
+                    .into_iter()
+                    .find(|x| x.is_file())
+                    .ok_or(format!("{}: no file for module \"{}\"", path.display(), name))?;
+                let visibility = if visibility.is_empty() {
+                    String::new()
+                } else {
+                    format!("{} ", visibility)
+                };
+                res += &format!("{}mod {} {{\n{}}}\n", visibility, name, inline_module(&file, false)?);
+            }
+            None => {
+                res += line;
+                res.push('\n');
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Removes "extern crate" of the inlined crates with their "#[macro_use]"
+fn remove_extern_crates(code: &str, crates: &BTreeMap<String, PathBuf>) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in code.lines() {
+        let name = line
+            .trim()
+            .strip_prefix("extern crate ")
+            .and_then(|x| x.strip_suffix(';'));
+        if name.is_some_and(|x| crates.contains_key(x.trim())) {
+            if lines.last().is_some_and(|x| x.trim() == "#[macro_use]") {
+                lines.pop();
+            }
+        } else {
+            lines.push(line);
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Fixes paths after the crate "name" becomes a module: "crate::x" becomes "crate::name::x" and
+/// "other_crate::x" becomes "crate::other_crate::x". Exported macros are at the root of the bundle,
+/// so "crate::m!" is kept and "other_crate::m!" becomes "crate::m!". Strings and comments are kept as is
+fn rewrite_paths(code: &str, name: Option<&str>, crates: &BTreeMap<String, PathBuf>) -> String {
+    let mut res = String::new();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let skip = literal_len(rest);
+        if skip > 0 {
+            res += &rest[..skip];
+            rest = &rest[skip..];
+            continue;
+        }
+        let prev = res.chars().last();
+        let word_start = !prev.is_some_and(|x| x.is_alphanumeric() || x == '_' || x == '$' || x == ':');
+        let root = ["$crate::", "crate::"]
+            .into_iter()
+            .find(|x| (word_start || x.starts_with('$')) && rest.starts_with(x));
+        if let Some(root) = root {
+            res += root;
+            rest = &rest[root.len()..];
+            if let (Some(name), false) = (name, is_macro_call(rest)) {
+                res += &format!("{}::", name);
+            }
+            continue;
+        }
+        let other = crates
+            .keys()
+            .map(|x| format!("{}::", x))
+            .find(|x| word_start && rest.starts_with(x.as_str()));
+        if let Some(other) = other {
+            rest = &rest[other.len()..];
+            res += "crate::";
+            if !is_macro_call(rest) {
+                res += &other;
+            }
+            continue;
+        }
+        res.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    res
+}
+
+/// Length of the string, char literal or "//" comment at the start of the code, 0 if there is none
+fn literal_len(code: &str) -> usize {
+    if code.starts_with("//") {
+        return code.find('\n').unwrap_or(code.len());
+    }
+    let quote = match code.chars().next() {
+        Some('"') => '"',
+        // not a lifetime
+        Some('\'') if code[1..].starts_with('\\') || code[1..].chars().nth(1) == Some('\'') => '\'',
+        _ => return 0,
+    };
+    let mut escaped = false;
+    for (i, c) in code.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return i + 1,
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+/// Whether the code starts with "name!"
+fn is_macro_call(code: &str) -> bool {
+    let len = code
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(code.len());
+    len > 0 && code[len..].starts_with('!')
+}
+
+fn contains_word(code: &str, word: &str) -> bool {
+    code.match_indices(word).any(|(i, _)| {
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        !code[..i].chars().last().is_some_and(is_ident) && !code[i + word.len()..].chars().next().is_some_and(is_ident)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle_cpp(files: &[(&str, &str)]) -> Result<String, String> {
+        let dir = std::env::temp_dir().join(format!("cpr-bundle-{}-{}", std::process::id(), files[0].0));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, data) in files {
+            fs::write(dir.join(name), data).unwrap();
+        }
+        let mut bundler = CppBundler {
+            libs_path: None,
+            strip: HashSet::from(["HOME".to_string()]),
+            included: HashSet::new(),
+            res: String::new(),
+        };
+        let res = bundler.add_file(&dir.join(files[0].0)).map(|_| bundler.res);
+        fs::remove_dir_all(&dir).unwrap();
+        res
+    }
+
+    #[test]
+    fn cpp_strip_branches() {
+        let main = "\
+#ifdef HOME
+debug
+#elif defined(LOCAL)
+local
+#else
+judge
+#endif
+#ifndef HOME // comment
+  #ifdef X
+x
+  #endif
+#endif
+";
+        assert_eq!(
+            bundle_cpp(&[("strip.cpp", main)]).unwrap(),
+            "#if defined(LOCAL)\nlocal\n#else\njudge\n#endif\n  #ifdef X\nx\n  #endif\n"
+        );
+        assert!(bundle_cpp(&[("unclosed.cpp", "#ifdef HOME\n")]).is_err());
+        assert!(bundle_cpp(&[("unopened.cpp", "#endif\n")]).is_err());
+    }
+
+    #[test]
+    fn cpp_includes_once() {
+        let main = "#include <vector>\n#include \"a.h\"\n#include \"b.h\"\nmain\n";
+        let a = "#pragma once\n#include \"b.h\"\na\n";
+        let b = "#pragma once\nb\n";
+        assert_eq!(
+            bundle_cpp(&[("include.cpp", main), ("a.h", a), ("b.h", b)]).unwrap(),
+            "#include <vector>\nb\na\nmain\n"
+        );
+    }
+
+    #[test]
+    fn path_values() {
+        assert_eq!(path_value(" { path = \"../io\" }"), Some("../io".to_string()));
+        assert_eq!(path_value(" {\"path\"=\"a\\\\b\\\"c\"}"), Some("a\\b\"c".to_string()));
+        assert_eq!(path_value(" { version = \"1\", path = \"x\" }"), Some("x".to_string()));
+        assert_eq!(path_value(" { xpath = \"x\" }"), None);
+        assert_eq!(path_value(" \"1.0\""), None);
+    }
+
+    #[test]
+    fn rewritten_paths() {
+        let crates = BTreeMap::from([("rlib_io".to_string(), PathBuf::new())]);
+        let code = "use crate::a; rlib_io::f(); rlib_io::m!(); $crate::b // crate::c\n\"crate::d\" '\"' crate::e";
+        assert_eq!(
+            rewrite_paths(code, Some("lib"), &crates),
+            "use crate::lib::a; crate::rlib_io::f(); crate::m!(); $crate::lib::b // crate::c\n\"crate::d\" '\"' crate::lib::e"
+        );
+    }
+}
@@ -1,11 +1,11 @@
-use std::collections::HashMap;
-
 use clap::{Parser, Subcommand};
 use draw_graph::GraphArgs;
 use draw_matrix::MatrixArgs;
 use draw_points::PointsArgs;
 use draw_tree::TreeArgs;
 
+use crate::task::Task;
+
 mod draw_graph;
 mod draw_matrix;
 mod draw_points;
@@ -29,11 +29,11 @@ pub struct DrawArgs {
     option: Options,
 }
 
-pub fn draw(args: DrawArgs, _params: &HashMap<String, String>) {
+pub fn draw(args: DrawArgs, _task: &Task) {
     match args.option {
-        Options::Points(args) => draw_points::draw(args, _params),
-        Options::Tree(args) => draw_tree::draw(args, _params),
-        Options::Graph(args) => draw_graph::draw(args, _params),
-        Options::Matrix(args) => draw_matrix::draw(args, _params),
+        Options::Points(args) => draw_points::draw(args, _task),
+        Options::Tree(args) => draw_tree::draw(args, _task),
+        Options::Graph(args) => draw_graph::draw(args, _task),
+        Options::Matrix(args) => draw_matrix::draw(args, _task),
     }
 }
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use std::io::{self};
 
 use clap::Parser;
@@ -14,6 +12,8 @@ use druid::{
     AppLauncher, Color, Command, Data, Lens, MouseButton, Point, Rect, Selector, Size, Target, WidgetExt, WindowDesc,
 };
 
+use crate::task::Task;
+
 const PADDING: f64 = 8.0;
 
 // for graph nodes
@@ -518,7 +518,7 @@ pub struct GraphArgs {
     directed: bool,
 }
 
-pub fn draw(args: GraphArgs, _params: &HashMap<String, String>) {
+pub fn draw(args: GraphArgs, _task: &Task) {
     let mut app_data = AppData {
         g: Rc::new(Vec::new()),
         vertex_info: Rc::new(Vec::new()),
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use std::io::{self};
 
 use std::rc::Rc;
@@ -11,6 +9,8 @@ use druid::widget::prelude::*;
 use druid::widget::{CrossAxisAlignment, Flex, MainAxisAlignment, Widget};
 use druid::{AppLauncher, Color, Data, Lens, MouseButton, Point, Rect, Size, WidgetExt, WindowDesc};
 
+use crate::task::Task;
+
 const PADDING: f64 = 8.0;
 
 const WIDTH: f64 = 0.01;
@@ -183,7 +183,7 @@ pub struct MatrixArgs {
     chars: bool,
 }
 
-pub fn draw(args: MatrixArgs, _params: &HashMap<String, String>) {
+pub fn draw(args: MatrixArgs, _task: &Task) {
     let mut app_data = AppData {
         mt: Rc::new(Vec::new()),
         chars: args.chars,
@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::thread;
 
 use std::io::{self};
@@ -11,6 +10,8 @@ use druid::widget::prelude::*;
 use druid::widget::{Checkbox, CrossAxisAlignment, Flex, MainAxisAlignment, Widget};
 use druid::{AppLauncher, Color, Data, Lens, MouseButton, Point, Rect, Size, WidgetExt, WindowDesc};
 
+use crate::task::Task;
+
 const PADDING: f64 = 8.0;
 
 #[derive(Clone, Lens, Data)]
@@ -254,7 +255,7 @@ pub struct PointsArgs {
     non: bool,
 }
 
-pub fn draw(args: PointsArgs, _params: &HashMap<String, String>) {
+pub fn draw(args: PointsArgs, _task: &Task) {
     let ptr = Arc::new(Mutex::new(Vec::<(f64, f64)>::new()));
     let thread_ptr = ptr.clone();
 
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use std::io::{self};
 
 use std::rc::Rc;
@@ -13,6 +11,8 @@ use druid::{
     AppLauncher, Color, Command, Data, Lens, MouseButton, Point, Rect, Selector, Size, Target, WidgetExt, WindowDesc,
 };
 
+use crate::task::Task;
+
 const PADDING: f64 = 8.0;
 
 // for tree nodes
@@ -526,7 +526,7 @@ pub struct TreeArgs {
     ugly_edges: bool,
 }
 
-pub fn draw(args: TreeArgs, _params: &HashMap<String, String>) {
+pub fn draw(args: TreeArgs, _task: &Task) {
     let mut app_data = AppData {
         g: Rc::new(Vec::new()),
         ugly_edges: args.ugly_edges,
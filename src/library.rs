@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+
+use crate::bundle::{find_manifest, path_dependencies};
+use crate::task::Task;
+use crate::{get_default_file_extension, get_settings, DEFAULT_FILE_NAME};
+
+/// Line in the C++ solution, after which "cpr lib add" inserts includes
+const LIB_MARKER: &str = "// cpr lib";
+
+/// Folders of "libs_path" with Rust crates
+const CRATE_FOLDERS: [&str; 2] = ["rlib", "external"];
+
+#[derive(Subcommand)]
+enum LibOptions {
+    /// List C++ headers and Rust crates from "libs_path"
+    List,
+
+    /// Add a snippet to the solution
+    ///
+    /// For C++ the "#include" of the header is inserted after the "// cpr lib" line,
+    /// for Rust the crate is added to [dependencies] in Cargo.toml.
+    Add(LibAddArgs),
+}
+
+#[derive(Parser)]
+pub struct LibArgs {
+    #[command(subcommand)]
+    option: LibOptions,
+}
+
+#[derive(Parser)]
+struct LibAddArgs {
+    /// Header path (e.g. "ds/dsu.h" or "dsu") or crate name (e.g. "rlib_io" or "io")
+    name: String,
+
+    /// Source file, "main" of the task by default
+    #[arg(short, long)]
+    file: Option<String>,
+}
+
+pub fn lib(args: LibArgs, task: &Task) {
+    let libs_path = match get_settings().config().libs_path.clone() {
+        Some(x) => PathBuf::from(x),
+        None => {
+            eprintln!("You need to set \"libs_path\" in settings, e.g. \"cpr config set libs_path /path/to/libs\"");
+            std::process::exit(1);
+        }
+    };
+
+    match args.option {
+        LibOptions::List => {
+            println!("C++ headers:");
+            for header in cpp_headers(&libs_path) {
+                println!("  {}", header);
+            }
+            println!("Rust crates:");
+            for folder in CRATE_FOLDERS {
+                for (name, path) in rust_crates(&libs_path.join(folder)) {
+                    println!("  {} ({})", name, path.display());
+                }
+            }
+        }
+        LibOptions::Add(args) => {
+            let filename = args.file.or(task.main.clone()).unwrap_or_else(|| {
+                format!(
+                    "{}.{}",
+                    DEFAULT_FILE_NAME,
+                    task.lang.clone().unwrap_or_else(get_default_file_extension)
+                )
+            });
+            let result = if filename.ends_with(".rs") {
+                add_crate(&libs_path, &args.name, &find_manifest(Path::new(&filename)))
+            } else {
+                add_header(&libs_path, &args.name, Path::new(&filename))
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Paths of C++ files in "libs_path" relative to it, without the crate folders
+pub fn cpp_headers(libs_path: &Path) -> Vec<String> {
+    fn walk(dir: &Path, prefix: &str, res: &mut Vec<String>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(x) => x,
+            Err(_) => return,
+        };
+        for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                if !(prefix.is_empty() && (CRATE_FOLDERS.contains(&name.as_str()) || name == "target")) {
+                    walk(&path, &format!("{}{}/", prefix, name), res);
+                }
+            } else if ["h", "hpp", "cpp"]
+                .iter()
+                .any(|x| path.extension().is_some_and(|y| y == *x))
+            {
+                res.push(format!("{}{}", prefix, name));
+            }
+        }
+    }
+    let mut res = Vec::new();
+    walk(libs_path, "", &mut res);
+    res.sort();
+    res
+}
+
+/// Crates in the subfolders of "folder" with their names from Cargo.toml, sorted by name
+pub fn rust_crates(folder: &Path) -> Vec<(String, PathBuf)> {
+    let mut res = Vec::new();
+    let entries = match fs::read_dir(folder) {
+        Ok(x) => x,
+        Err(_) => return res,
+    };
+    for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+        let lines = match fs::read_to_string(path.join("Cargo.toml")) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        let name = lines.lines().find_map(|line| {
+            let value = line.strip_prefix("name")?.trim_start().strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_string())
+        });
+        if let Some(name) = name {
+            res.push((name, path));
+        }
+    }
+    res.sort();
+    res
+}
+
+/// Line for [dependencies] in Cargo.toml
+pub fn dependency_line(name: &str, path: &Path) -> String {
+    let path = path.display().to_string().replace('\\', "\\\\").replace('"', "\\\"");
+    format!("{} = {{ \"path\" = \"{}\" }}", name, path)
+}
+
+fn add_header(libs_path: &Path, name: &str, file: &Path) -> Result<(), String> {
+    let headers = cpp_headers(libs_path);
+    let mut found: Vec<&String> = headers.iter().filter(|x| *x == name).collect();
+    if found.is_empty() {
+        found = headers
+            .iter()
+            .filter(|x| {
+                let path = Path::new(x.as_str());
+                path.with_extension("").to_str() == Some(name) || path.file_stem().is_some_and(|x| x == name)
+            })
+            .collect();
+    }
+    let header = match found.as_slice() {
+        [x] => *x,
+        [] => {
+            return Err(format!(
+                "No header \"{}\" in {}, see \"cpr lib list\"",
+                name,
+                libs_path.display()
+            ))
+        }
+        _ => return Err(format!("\"{}\" is ambiguous: {:?}", name, found)),
+    };
+
+    let include = format!("#include \"{}\"", libs_path.join(header).display());
+    let data = fs::read_to_string(file).map_err(|e| format!("Can't read {}: {}", file.display(), e))?;
+    let mut lines: Vec<&str> = data.lines().collect();
+    if lines.iter().any(|x| x.trim() == include) {
+        println!("{} is already included", header);
+        return Ok(());
+    }
+    let marker = lines.iter().position(|x| x.trim() == LIB_MARKER).ok_or(format!(
+        "No \"{}\" line in {}, add it where the includes should go",
+        LIB_MARKER,
+        file.display()
+    ))?;
+    // after the includes added before
+    let mut position = marker + 1;
+    while position < lines.len() && lines[position].trim_start().starts_with("#include") {
+        position += 1;
+    }
+    lines.insert(position, &include);
+    fs::write(file, lines.join("\n") + "\n").map_err(|e| format!("Can't write {}: {}", file.display(), e))?;
+    println!("Included {} into {}", header, file.display());
+    Ok(())
+}
+
+fn add_crate(libs_path: &Path, name: &str, manifest: &Path) -> Result<(), String> {
+    let crates: Vec<(String, PathBuf)> = CRATE_FOLDERS
+        .iter()
+        .flat_map(|folder| rust_crates(&libs_path.join(folder)))
+        .collect();
+    let (name, path) = crates
+        .iter()
+        .find(|(x, _)| x == name)
+        .or_else(|| crates.iter().find(|(x, _)| *x == format!("rlib_{}", name)))
+        .or_else(|| {
+            crates
+                .iter()
+                .find(|(_, path)| path.file_name().is_some_and(|x| x == name))
+        })
+        .ok_or(format!(
+            "No crate \"{}\" in {}, see \"cpr lib list\"",
+            name,
+            libs_path.display()
+        ))?;
+
+    if path_dependencies(manifest).contains_key(&name.replace('-', "_")) {
+        println!("{} is already in {}", name, manifest.display());
+        return Ok(());
+    }
+    let data = fs::read_to_string(manifest).map_err(|e| format!("Can't read {}: {}", manifest.display(), e))?;
+    let mut lines: Vec<&str> = data.lines().collect();
+    let section = lines
+        .iter()
+        .position(|x| x.trim() == "[dependencies]")
+        .ok_or(format!("No [dependencies] in {}", manifest.display()))?;
+    let mut position = section + 1;
+    while position < lines.len() && !lines[position].trim_start().starts_with('[') {
+        position += 1;
+    }
+    // before the empty lines at the end of the section
+    while position > section + 1 && lines[position - 1].trim().is_empty() {
+        position -= 1;
+    }
+    let line = dependency_line(name, path);
+    lines.insert(position, &line);
+    fs::write(manifest, lines.join("\n") + "\n").map_err(|e| format!("Can't write {}: {}", manifest.display(), e))?;
+    println!("Added {} to {}", name, manifest.display());
+    Ok(())
+}
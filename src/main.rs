@@ -1,14 +1,17 @@
 use approx::ApproxArgs;
-use clap::Parser;
+use bundle::BundleArgs;
+use clap::{Parser, Subcommand};
 use draw::DrawArgs;
+use library::LibArgs;
 use serde::{Deserialize, Serialize};
+use submit::{StatusArgs, SubmitArgs};
 use subprocess::{ExitStatus, Popen, PopenConfig, Redirection};
 
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
 use std::env;
 use std::fs;
 use std::io::{self, BufReader, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use soup::prelude::*;
@@ -21,51 +24,54 @@ use serde_json::Value;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 use std::io::prelude::*;
-use std::net::TcpListener;
+use std::net::{TcpListener, TcpStream};
 
 use std::process::{Command, Stdio};
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::OnceLock;
 use std::thread;
 
 use threadpool::ThreadPool;
 
 mod approx;
+mod bundle;
 mod draw;
+mod library;
+mod source;
+mod submit;
+mod task;
+mod template;
 mod util;
 
+use crate::source::Source;
+use crate::task::{Checker, Task};
 use crate::util::*;
 
-const LOCAL_PARAMS_NAME: &str = "params";
+/// Environment variable with the path to settings.json, overrides the default location
+const SETTINGS_ENV: &str = "CPR_CONFIG";
 
-#[cfg(not(target_os = "windows"))]
-const SETTINGS_FILE: &str = "/home/maksim/.config/cpr/settings.json";
-
-#[cfg(target_os = "windows")]
-const SETTINGS_FILE: &str = "C:/Users/magor/AppData/Local/cp_rust/settings.json";
+/// Environment variable with the folder of user templates, overrides "templates_path" from config
+const TEMPLATES_ENV: &str = "CPR_TEMPLATES";
 
 const DEFAULT_FILE_NAME: &str = "main";
 const DEFAULT_FILE_EXTENSION: &str = "cpp";
 const OPEN_FILE_ON_CREATION: bool = true;
 const DEFAULT_TIMEOUT: f64 = 5.;
+const COMPANION_PORT: u16 = 10046;
+const SANDBOX_DIR: &str = "cpr_sandbox";
+/// Polygon package downloaded by "cpr fetch-tests" and the folder it's unpacked to
+const PACKAGE_ZIP: &str = "cpr_package.zip";
+const PACKAGE_DIR: &str = "cpr_package";
 
-enum ProblemSource {
-    None,
-    Codeforces(String, String),
-    CodeChef(String, String),
-    AtCoder(String, String),
-    CodinGamePuzzle(String),
-    Cses(String),
-}
-
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
 struct BuildProfile {
     cmd: Vec<String>,
 }
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct Config {
     #[serde(default)]
     lang: Option<String>,
@@ -75,8 +81,28 @@ struct Config {
     vscode: BTreeMap<String, serde_json::Value>,
     #[serde(default)]
     libs_path: Option<String>,
+    /// Folder with templates, that override the built-in ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    templates_path: Option<String>,
     #[serde(default)]
     build: BTreeMap<String, BuildProfile>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    submit_lang: BTreeMap<String, String>,
+    /// "[[author]]" in templates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    /// Custom variables for templates
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    vars: BTreeMap<String, String>,
+    /// Defaults for task.json of all problems
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    task: Option<Task>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Auth {
+    login: String,
+    password: String,
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -85,15 +111,23 @@ struct Settings {
     profile: String,
     #[serde(default)]
     config: BTreeMap<String, Config>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    auth: BTreeMap<String, Auth>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<Source>,
 }
 
+/// Profile from task.json of the current folder, overrides "profile" from settings
+static PROFILE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
 impl Settings {
-    fn config(&self) -> &Config {
-        &self.config[&self.profile]
+    /// Profile for the current folder, not necessarily the one saved in settings
+    fn active_profile(&self) -> &str {
+        PROFILE_OVERRIDE.get().unwrap_or(&self.profile)
     }
 
-    fn config_mut(&mut self) -> &mut Config {
-        self.config.get_mut(&self.profile).unwrap()
+    fn config(&self) -> &Config {
+        &self.config[self.active_profile()]
     }
 }
 
@@ -133,17 +167,28 @@ struct StressTestArgs {
     #[arg(long)]
     checkf: Option<String>,
 
-    /// Epsilon for comparison
+    /// Epsilon for comparison, "comparator" of the task by default
     #[arg(short, long)]
     eps: Option<f64>,
 
-    /// Timeout in seconds
-    #[arg(short, long, default_value_t = DEFAULT_TIMEOUT)]
-    timeout: f64,
+    /// Timeout in seconds, time limit of the task with --limits
+    #[arg(short, long)]
+    timeout: Option<f64>,
+
+    /// Use time limit of the task as the timeout of main, and on unix its memory limit
+    /// as the limit of virtual memory (which breaks binaries with sanitizers)
+    #[arg(long)]
+    limits: bool,
 }
 
-fn stress_test(args: StressTestArgs, _params: &HashMap<String, String>) {
+fn stress_test(args: StressTestArgs, task: &Task) {
     let mut seed: i32 = args.seed;
+    let timeout = args.timeout.or(task.timeout).unwrap_or(DEFAULT_TIMEOUT);
+    let main_timeout = args
+        .timeout
+        .or(task.time_limit_secs().filter(|_| args.limits))
+        .unwrap_or(timeout);
+    let memory_limit = task.memory_limit.filter(|_| args.limits);
     let filename = args.filename.unwrap_or(String::from(DEFAULT_FILE_NAME));
     let easy_str = args.easy.unwrap_or(String::from("easy"));
     let gen_str = args.gen.unwrap_or(String::from("gen"));
@@ -158,7 +203,7 @@ fn stress_test(args: StressTestArgs, _params: &HashMap<String, String>) {
             &[&fix_unix_filename(&gen_str), &seed.to_string()],
             "",
             "in",
-            Some(args.timeout),
+            Some(timeout),
         );
         if !result.success() {
             println!("X  [seed = {}]", seed);
@@ -168,7 +213,7 @@ fn stress_test(args: StressTestArgs, _params: &HashMap<String, String>) {
         io::stdout().flush().unwrap();
 
         if !args.check {
-            let result = run_and_wait(&[&easy_str], "in", "ans", Some(args.timeout));
+            let result = run_and_wait(&[&easy_str], "in", "ans", Some(timeout));
             if !result.success() {
                 println!("X  [seed = {}]", seed);
                 break;
@@ -177,7 +222,7 @@ fn stress_test(args: StressTestArgs, _params: &HashMap<String, String>) {
             io::stdout().flush().unwrap();
         }
 
-        let result = run_and_wait(&[&filename], "in", "out", Some(args.timeout));
+        let result = run_main(&filename, "in", "out", main_timeout, memory_limit, task);
         if !result.success() {
             println!("X  [seed = {}]", seed);
             break;
@@ -189,7 +234,7 @@ fn stress_test(args: StressTestArgs, _params: &HashMap<String, String>) {
             let inout = [fs::read_to_string("in").unwrap(), fs::read_to_string("out").unwrap()].concat();
             fs::File::create("inout").unwrap().write(inout.as_bytes()).unwrap();
 
-            let result = run_and_wait(&[&check_str], "inout", "ans", Some(args.timeout));
+            let result = run_and_wait(&[&check_str], "inout", "ans", Some(timeout));
             if !result.success() {
                 println!("X  [seed = {}]", seed);
 
@@ -208,7 +253,7 @@ fn stress_test(args: StressTestArgs, _params: &HashMap<String, String>) {
             io::stdout().flush().unwrap();
         }
 
-        if !args.check && !compare_output("out", "ans", args.eps) {
+        if !args.check && !compare_output("out", "ans", args.eps.or(task.eps())) {
             println!("   failed   [seed = {}]", seed);
             if !args.quiet {
                 println!("========== in  ==========");
@@ -271,21 +316,19 @@ struct IStressTestArgs {
     eps: Option<f64>,
 }
 
-fn stress_test_inline(args: IStressTestArgs, _params: &HashMap<String, String>) {
+fn stress_test_inline(args: IStressTestArgs, _task: &Task) {
     let seed: i32 = args.seed;
     let filename = args.filename.unwrap_or(String::from(DEFAULT_FILE_NAME));
     let easy_str = args.easy.unwrap_or(String::from("easy"));
     let gen_str = args.gen.unwrap_or(String::from("gen"));
     let check_str = args.checkf.unwrap_or(String::from("check"));
 
-    let mut template_file = get_templates_path();
-    if args.check {
-        template_file.push("stress_test_check_template.cpp");
+    let template_file = if args.check {
+        "stress_test_check_template.cpp"
     } else {
-        template_file.push("stress_test_template.cpp");
-    }
-    let template_file = template_file.to_str().unwrap();
-    let template = fs::read_to_string(template_file).unwrap().trim().to_string();
+        "stress_test_template.cpp"
+    };
+    let template = read_template(template_file).unwrap().trim().to_string();
     let template = template.split('\n').map(|x| x.trim_end()).collect::<Vec<_>>();
 
     let mut result: Vec<String> = Vec::new();
@@ -377,7 +420,8 @@ struct TestArgs {
     #[arg(short, long)]
     indices: Option<String>,
 
-    /// Run checker on output instead of comparing with ans
+    /// Run checker on output instead of comparing with ans. Always enabled
+    /// if the task has a "checker"
     #[arg(long, default_value_t)]
     check: bool,
 
@@ -385,22 +429,37 @@ struct TestArgs {
     #[arg(long)]
     checkf: Option<String>,
 
-    /// Epsilon for comparison
+    /// Epsilon for comparison, "comparator" of the task by default
     #[arg(short, long)]
     eps: Option<f64>,
 
-    /// Timeout in seconds
-    #[arg(short, long, default_value_t = DEFAULT_TIMEOUT)]
-    timeout: f64,
+    /// Timeout in seconds, time limit of the task with --limits
+    #[arg(short, long)]
+    timeout: Option<f64>,
+
+    /// Use time limit of the task as the timeout of main, and on unix its memory limit
+    /// as the limit of virtual memory (which breaks binaries with sanitizers)
+    #[arg(long)]
+    limits: bool,
 
     /// Print output and answer side by side
     #[arg(long)]
     near: bool,
 }
 
-fn run_tests(args: TestArgs, _params: &HashMap<String, String>) {
+fn run_tests(args: TestArgs, task: &Task) {
     let filename = args.filename.unwrap_or(String::from(DEFAULT_FILE_NAME));
+    let timeout = args.timeout.or(task.timeout).unwrap_or(DEFAULT_TIMEOUT);
+    let main_timeout = args
+        .timeout
+        .or(task.time_limit_secs().filter(|_| args.limits))
+        .unwrap_or(timeout);
+    let memory_limit = task.memory_limit.filter(|_| args.limits);
     let check_str = args.checkf.unwrap_or(String::from("check"));
+    // testlib checkers are run as "check in out ans", see "cpr fetch-tests"
+    let testlib_checker = task.checker == Some(Checker::Testlib);
+    let check = args.check || task.checker.is_some();
+    let eps = args.eps.or(task.eps());
 
     let mut tests = get_available_tests();
     tests.sort();
@@ -442,11 +501,13 @@ fn run_tests(args: TestArgs, _params: &HashMap<String, String>) {
         print!("Case #{:<6}", format!("{}:", test));
         io::stdout().flush().unwrap();
 
-        let result = run_and_wait(
-            &[&filename],
+        let result = run_main(
+            &filename,
             &["in", &test.to_string()].concat(),
             &["out", &test.to_string()].concat(),
-            Some(args.timeout),
+            main_timeout,
+            memory_limit,
+            task,
         );
         let duration = now.elapsed().as_millis();
         print!("{:>5} ms   ", duration);
@@ -474,24 +535,35 @@ fn run_tests(args: TestArgs, _params: &HashMap<String, String>) {
                 println!("========== err ==========");
                 println!("{}", read_lines_trim("err").join("\n"));
             }
-        } else if args.check {
-            let mut in_string = fs::read_to_string(&["in", &test.to_string()].concat()).unwrap();
-            if in_string.len() != 0 && in_string.as_bytes()[in_string.len() - 1] != b'\n' {
-                in_string += "\n";
-            }
-            let out_string = fs::read_to_string(&["out", &test.to_string()].concat()).unwrap();
-            let inout = [in_string, out_string].concat();
-            fs::File::create(&["inout", &test.to_string()].concat())
-                .unwrap()
-                .write(inout.as_bytes())
-                .unwrap();
-
-            let result = run_and_wait(
-                &[&check_str],
-                &["inout", &test.to_string()].concat(),
-                &["ans", &test.to_string()].concat(),
-                Some(args.timeout),
-            );
+        } else if check {
+            let result = if testlib_checker {
+                run_and_wait(
+                    &[
+                        &fix_unix_filename(&check_str),
+                        &["in", &test.to_string()].concat(),
+                        &["out", &test.to_string()].concat(),
+                        &["ans", &test.to_string()].concat(),
+                    ],
+                    "",
+                    "",
+                    Some(timeout),
+                )
+            } else {
+                let mut in_string = fs::read_to_string(["in", &test.to_string()].concat()).unwrap();
+                if !in_string.is_empty() && !in_string.ends_with('\n') {
+                    in_string += "\n";
+                }
+                let out_string = fs::read_to_string(["out", &test.to_string()].concat()).unwrap();
+                let inout = [in_string, out_string].concat();
+                fs::write(["inout", &test.to_string()].concat(), inout).unwrap();
+
+                run_and_wait(
+                    &[&check_str],
+                    &["inout", &test.to_string()].concat(),
+                    &["ans", &test.to_string()].concat(),
+                    Some(timeout),
+                )
+            };
             if !result.success() {
                 stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
                 writeln!(&mut stdout, "failed").unwrap();
@@ -504,6 +576,10 @@ fn run_tests(args: TestArgs, _params: &HashMap<String, String>) {
                     println!("{}", read_lines_trim(&["out", &test.to_string()].concat()).join("\n"));
                     println!("========== ans ==========");
                     println!("{}", read_lines_trim(&["ans", &test.to_string()].concat()).join("\n"));
+                    if testlib_checker {
+                        println!("========== check ==========");
+                        println!("{}", read_lines_trim("err").join("\n"));
+                    }
                 }
             } else {
                 stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green))).unwrap();
@@ -517,7 +593,7 @@ fn run_tests(args: TestArgs, _params: &HashMap<String, String>) {
         } else if !compare_output(
             &["out", &test.to_string()].concat(),
             &["ans", &test.to_string()].concat(),
-            args.eps,
+            eps,
         ) {
             stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
             writeln!(&mut stdout, "failed").unwrap();
@@ -564,7 +640,7 @@ fn run_tests(args: TestArgs, _params: &HashMap<String, String>) {
                         }
                         println!("|");
                     }
-                } else if args.eps.is_some() {
+                } else if eps.is_some() {
                     println!("========== out ==========");
                     println!("{}", read_lines_trim(&["out", &test.to_string()].concat()).join("\n"));
                     println!("========== ans ==========");
@@ -618,12 +694,32 @@ struct InteractArgs {
     /// Number of spaces before printing "judge:"
     #[arg(long, default_value_t = 20)]
     tab_size: usize,
+
+    /// Number of cases to run, runs until the first failure if not specified
+    #[arg(short = 'n', long)]
+    cases: Option<usize>,
+
+    /// Count only lines from main starting with this prefix as queries (e.g. "?")
+    #[arg(long)]
+    query_prefix: Option<String>,
+
+    /// Maximum number of queries, case fails if main makes more
+    #[arg(long)]
+    max_queries: Option<usize>,
+
+    /// Number of main/interactor pairs to run concurrently. The first failing
+    /// seed is replayed with --debug
+    #[arg(short, long, default_value_t = 1)]
+    jobs: usize,
 }
 
-fn interact(args: InteractArgs, _params: &HashMap<String, String>) {
-    let mut seed = args.seed;
-    let filename = args.filename.unwrap_or(String::from(DEFAULT_FILE_NAME));
-    let interact = args.interactf.unwrap_or(String::from("interact"));
+fn interact(args: InteractArgs, _task: &Task) {
+    if args.jobs == 0 {
+        eprintln!("--jobs must be at least 1");
+        std::process::exit(1);
+    }
+    let filename = args.filename.clone().unwrap_or(String::from(DEFAULT_FILE_NAME));
+    let interact = args.interactf.clone().unwrap_or(String::from("interact"));
 
     let mut filename_vec: Vec<String> = Vec::new();
     filename_vec.extend(filename.split_whitespace().map(|x| String::from(x)).collect::<Vec<_>>());
@@ -639,101 +735,236 @@ fn interact(args: InteractArgs, _params: &HashMap<String, String>) {
         interact_vec[0] = ["./", &interact_vec[0]].concat().to_string();
     }
 
+    let main_cmd = filename_vec[..].join(" ");
+    let interact_cmd = interact_vec[..].join(" ");
+
+    if args.jobs > 1 && !args.debug {
+        interact_parallel(main_cmd, interact_cmd, args);
+        return;
+    }
+
     let mut stdout = StandardStream::stdout(ColorChoice::Always);
 
+    let mut seed = args.seed;
     let mut case = 1;
+    let mut max_queries: Option<(usize, i32)> = None;
 
     loop {
-        let (mut child_shell1, mut child1_in, rx_out1, rx_err1, tx_end11, tx_end12) =
-            run_interactive(&filename_vec[..].join(" "));
-        let (mut child_shell2, mut child2_in, rx_out2, rx_err2, tx_end21, tx_end22) =
-            run_interactive(&[&interact_vec[..], &[seed.to_string()]].concat().join(" "));
-
         if !args.debug {
             print!("\rCase #{}: [seed = {}] ", case, seed);
             io::stdout().flush().unwrap();
         }
 
-        let mut end1 = false;
-        let mut end2 = false;
-        loop {
-            if let Ok(x) = child_shell1.try_wait() {
-                if !end1 && !x.is_none() {
-                    if !x.unwrap().success() {
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
-                        writeln!(&mut stdout, "main exitted with {}", x.unwrap()).unwrap();
-                        stdout.set_color(&ColorSpec::new()).unwrap();
-                        std::process::exit(0);
-                    }
-                    end1 = true;
-                }
-            }
-            if let Ok(x) = child_shell2.try_wait() {
-                if !end2 && !x.is_none() {
-                    if !x.unwrap().success() {
-                        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
-                        writeln!(&mut stdout, "judge exitted with {}", x.unwrap()).unwrap();
-                        stdout.set_color(&ColorSpec::new()).unwrap();
-                        std::process::exit(0);
-                    }
-                    end2 = true;
-                }
-            }
-            if end1 && end2 {
-                tx_end11.send(0).unwrap();
-                tx_end12.send(0).unwrap();
-                tx_end21.send(0).unwrap();
-                tx_end22.send(0).unwrap();
+        let result = interact_case(&main_cmd, &interact_cmd, seed, &args, args.debug, !args.quiet);
+
+        if max_queries.is_none_or(|(q, _)| result.queries > q) {
+            max_queries = Some((result.queries, seed));
+        }
+
+        if let Some(error) = result.error {
+            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
+            writeln!(&mut stdout, "{}", error).unwrap();
+            stdout.set_color(&ColorSpec::new()).unwrap();
+            println!("[seed = {}] queries = {}", seed, result.queries);
+            break;
+        }
+        if args.debug {
+            println!("queries = {}", result.queries);
+        } else {
+            print!("queries = {:<10}", result.queries);
+            io::stdout().flush().unwrap();
+        }
+
+        seed += 1;
+        case += 1;
+        if args.debug || args.cases.is_some_and(|n| case > n) {
+            break;
+        }
+    }
+
+    if let Some((queries, seed)) = max_queries {
+        println!("\nMax queries: {} [seed = {}]", queries, seed);
+    }
+}
+
+fn interact_parallel(main_cmd: String, interact_cmd: String, args: InteractArgs) {
+    let args = Arc::new(args);
+    let main_cmd = Arc::new(main_cmd);
+    let interact_cmd = Arc::new(interact_cmd);
+
+    let end_seed = args.cases.map(|n| args.seed + n as i32);
+    let next_seed = Arc::new(Mutex::new(args.seed));
+    let done = Arc::new(Mutex::new(0usize));
+    let max_queries: Arc<Mutex<Option<(usize, i32)>>> = Arc::new(Mutex::new(None));
+    let first_failed: Arc<Mutex<Option<(i32, String)>>> = Arc::new(Mutex::new(None));
+
+    let pool = ThreadPool::new(args.jobs);
+
+    for _ in 0..args.jobs {
+        let args = args.clone();
+        let main_cmd = main_cmd.clone();
+        let interact_cmd = interact_cmd.clone();
+        let next_seed = next_seed.clone();
+        let done = done.clone();
+        let max_queries = max_queries.clone();
+        let first_failed = first_failed.clone();
+
+        pool.execute(move || loop {
+            let seed = {
+                let mut next_seed = next_seed.lock().unwrap();
+                *next_seed += 1;
+                *next_seed - 1
+            };
+            if end_seed.is_some_and(|end| seed >= end) {
                 break;
             }
-            if let Ok(line) = rx_err1.try_recv() {
-                if args.debug {
-                    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow))).unwrap();
-                    writeln!(&mut stdout, "main: {}", line.trim()).unwrap();
-                    stdout.set_color(&ColorSpec::new()).unwrap();
-                }
+            // seeds after the first failure are not interesting anymore
+            if first_failed.lock().unwrap().as_ref().is_some_and(|(s, _)| *s < seed) {
+                break;
             }
-            if let Ok(line) = rx_out1.try_recv() {
-                if args.debug {
-                    writeln!(&mut stdout, "main: {}", line.trim()).unwrap();
-                }
-                child2_in.write(line.as_bytes()).unwrap();
-            }
-            if let Ok(line) = rx_err2.try_recv() {
-                if args.debug {
-                    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow))).unwrap();
-                    writeln!(&mut stdout, "{:w$}judge: {}", "", line.trim(), w = args.tab_size).unwrap();
-                    stdout.set_color(&ColorSpec::new()).unwrap();
-                } else if !args.quiet {
-                    writeln!(&mut stdout, "{}", line.trim()).unwrap();
+
+            let result = interact_case(&main_cmd, &interact_cmd, seed, &args, false, false);
+
+            {
+                let mut max_queries = max_queries.lock().unwrap();
+                if max_queries.is_none_or(|(q, _)| result.queries > q) {
+                    *max_queries = Some((result.queries, seed));
                 }
             }
-            if let Ok(line) = rx_out2.try_recv() {
-                if args.debug {
-                    writeln!(&mut stdout, "{:w$}judge: {}", "", line.trim(), w = args.tab_size).unwrap();
+            if let Some(error) = result.error {
+                let mut first_failed = first_failed.lock().unwrap();
+                if first_failed.as_ref().is_none_or(|(s, _)| seed < *s) {
+                    *first_failed = Some((seed, error));
                 }
-                child1_in.write(line.as_bytes()).unwrap();
             }
-        }
 
-        seed += 1;
-        case += 1;
-        if args.debug {
-            break;
+            let mut done = done.lock().unwrap();
+            *done += 1;
+            print!("\rCases done: {} [seed = {}] ", *done, seed);
+            io::stdout().flush().unwrap();
+        });
+    }
+
+    pool.join();
+    println!();
+
+    if let Some((seed, error)) = first_failed.lock().unwrap().take() {
+        let mut stdout = StandardStream::stdout(ColorChoice::Always);
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
+        writeln!(&mut stdout, "[seed = {}] {}", seed, error).unwrap();
+        stdout.set_color(&ColorSpec::new()).unwrap();
+
+        println!("Replaying [seed = {}]", seed);
+        let result = interact_case(&main_cmd, &interact_cmd, seed, &args, true, true);
+        if let Some(error) = result.error {
+            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
+            writeln!(&mut stdout, "{}", error).unwrap();
+            stdout.set_color(&ColorSpec::new()).unwrap();
         }
+        println!("queries = {}", result.queries);
+    }
+
+    let max_queries = *max_queries.lock().unwrap();
+    if let Some((queries, seed)) = max_queries {
+        println!("Max queries: {} [seed = {}]", queries, seed);
     }
 }
 
-#[derive(Parser, Default)]
-struct ParseArgs {
-    /// Ignore all settings and listen on port
-    #[arg(short, long)]
-    force: bool,
+struct InteractCaseResult {
+    queries: usize,
+    error: Option<String>,
+}
 
-    /// Parse contest. You also need to specify exactly one of --nA, --na, --n1
-    #[arg(long)]
-    contest: bool,
+fn interact_case(
+    main_cmd: &str,
+    interact_cmd: &str,
+    seed: i32,
+    args: &InteractArgs,
+    debug: bool,
+    echo: bool,
+) -> InteractCaseResult {
+    let (mut child_shell1, mut child1_in, rx_out1, rx_err1, tx_end11, tx_end12) = run_interactive(main_cmd);
+    let (mut child_shell2, mut child2_in, rx_out2, rx_err2, tx_end21, tx_end22) =
+        run_interactive(&[interact_cmd, &seed.to_string()].join(" "));
+
+    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+
+    let mut queries: usize = 0;
+    let mut error: Option<String> = None;
+    let mut end1 = false;
+    let mut end2 = false;
+    loop {
+        if let (false, Ok(Some(status))) = (end1, child_shell1.try_wait()) {
+            if !status.success() {
+                error = Some(format!("main exitted with {}", status));
+            }
+            end1 = true;
+        }
+        if let (false, Ok(Some(status))) = (end2, child_shell2.try_wait()) {
+            if !status.success() && error.is_none() {
+                error = Some(format!("judge exitted with {}", status));
+            }
+            end2 = true;
+        }
+        if error.is_some() {
+            let _ = child_shell1.kill();
+            let _ = child_shell2.kill();
+            end1 = true;
+            end2 = true;
+        }
+        if end1 && end2 {
+            tx_end11.send(0).unwrap();
+            tx_end12.send(0).unwrap();
+            tx_end21.send(0).unwrap();
+            tx_end22.send(0).unwrap();
+            break;
+        }
+        if let Ok(line) = rx_err1.try_recv() {
+            if debug {
+                stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow))).unwrap();
+                writeln!(&mut stdout, "main: {}", line.trim()).unwrap();
+                stdout.set_color(&ColorSpec::new()).unwrap();
+            }
+        }
+        if let Ok(line) = rx_out1.try_recv() {
+            if debug {
+                writeln!(&mut stdout, "main: {}", line.trim()).unwrap();
+            }
+            if args
+                .query_prefix
+                .as_ref()
+                .is_none_or(|p| line.trim_start().starts_with(p.as_str()))
+            {
+                queries += 1;
+                if let Some(max_queries) = args.max_queries.filter(|&m| queries > m) {
+                    error = Some(format!("main made more than {} queries", max_queries));
+                    continue;
+                }
+            }
+            child2_in.write_all(line.as_bytes()).unwrap();
+        }
+        if let Ok(line) = rx_err2.try_recv() {
+            if debug {
+                stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow))).unwrap();
+                writeln!(&mut stdout, "{:w$}judge: {}", "", line.trim(), w = args.tab_size).unwrap();
+                stdout.set_color(&ColorSpec::new()).unwrap();
+            } else if echo {
+                writeln!(&mut stdout, "{}", line.trim()).unwrap();
+            }
+        }
+        if let Ok(line) = rx_out2.try_recv() {
+            if debug {
+                writeln!(&mut stdout, "{:w$}judge: {}", "", line.trim(), w = args.tab_size).unwrap();
+            }
+            child1_in.write_all(line.as_bytes()).unwrap();
+        }
+    }
 
+    InteractCaseResult { queries, error }
+}
+
+#[derive(clap::Args, Clone)]
+struct ProblemCountArgs {
     /// Number of problems, with the first problem named "A"
     #[arg(long = "nA")]
     n_upper_a: Option<usize>,
@@ -746,123 +977,101 @@ struct ParseArgs {
     #[arg(long = "n1")]
     n_one: Option<usize>,
 
-    /// Print full responses
-    #[arg(long)]
-    echo: bool,
+    /// If the number of problems is not specified, stop after this many seconds without new problems
+    #[arg(long, default_value_t = 10.)]
+    wait: f64,
 }
 
-fn parse(args: ParseArgs, params: &HashMap<String, String>) {
-    let mut url: Option<String> = if params.contains_key("url") && !args.force {
-        Some(params.get("url").unwrap().clone())
-    } else {
-        None
-    };
-
-    let mut problem_names: Vec<String> = Vec::new();
-    if args.contest {
+impl ProblemCountArgs {
+    /// None if problem names should be taken from competitive companion
+    fn problem_names(&self) -> Option<Vec<String>> {
+        let mut problem_names: Vec<String> = Vec::new();
         let mut fill_from = |first: char, count: usize| {
             for i in 0..count {
                 problem_names.push(String::from((first as usize + i) as u8 as char));
             }
         };
-        match (args.n_one, args.n_upper_a, args.n_lower_a) {
+        match (self.n_one, self.n_upper_a, self.n_lower_a) {
+            (None, None, None) => return None,
             (Some(x), None, None) => fill_from('1', x),
             (None, Some(x), None) => fill_from('A', x),
             (None, None, Some(x)) => fill_from('a', x),
-            _ => panic!("Exactly one of --nA, --na, --n1 must be specified"),
+            _ => panic!("At most one of --nA, --na, --n1 can be specified"),
         }
+        Some(problem_names)
     }
+}
 
-    if let Some(ref url) = url {
-        if url.contains("codeforces.com") {
-            let response = reqwest::blocking::get(url).unwrap().text().unwrap();
-            let soup = Soup::new(&response);
-
-            let inputs: Vec<_> = soup
-                .tag("div")
-                .class("input")
-                .find_all()
-                .map(|x| x.tag("pre").find().unwrap().display())
-                .collect();
-            let inputs: Vec<_> = inputs
-                .iter()
-                .map(|x| {
-                    x.replace("<br>", "")
-                        .replace("</br>", "\n")
-                        .replace("<pre>", "")
-                        .replace("</pre>", "")
-                })
-                .collect();
-
-            let answers: Vec<_> = soup
-                .tag("div")
-                .class("output")
-                .find_all()
-                .map(|x| x.tag("pre").find().unwrap().display())
-                .collect();
-            let answers: Vec<_> = answers
-                .iter()
-                .map(|x| {
-                    x.replace("<br>", "")
-                        .replace("</br>", "\n")
-                        .replace("<pre>", "")
-                        .replace("</pre>", "")
-                })
-                .collect();
+#[derive(Parser)]
+struct ParseArgs {
+    /// Ignore all settings and listen on port
+    #[arg(short, long)]
+    force: bool,
 
-            for i in 0..inputs.len() {
-                let test = first_available_test();
-                fs::File::create(&["in", &test.to_string()].concat())
-                    .unwrap()
-                    .write(inputs[i].as_bytes())
-                    .unwrap();
-                if i < answers.len() {
-                    fs::File::create(&["ans", &test.to_string()].concat())
-                        .unwrap()
-                        .write(answers[i].as_bytes())
-                        .unwrap();
-                }
-            }
+    /// Parse contest. Problem names are taken from urls, unless one of --nA, --na, --n1 is specified
+    #[arg(long)]
+    contest: bool,
 
-            println!("Parsed {} tests from codeforces", inputs.len());
-            return;
-        }
-    }
+    #[command(flatten)]
+    count: ProblemCountArgs,
 
-    let create_tests_from_json = |data: &Value| {
-        if data["interactive"].as_bool().unwrap() {
-            println!("This is an interactive problem");
-            return;
-        }
+    /// Print full responses
+    #[arg(long)]
+    echo: bool,
 
-        let tests = data["tests"].as_array().unwrap();
+    /// Port to listen on for competitive companion
+    #[arg(long, default_value_t = COMPANION_PORT)]
+    port: u16,
+}
 
-        for test in tests.iter() {
-            let index = first_available_test();
-            let input = test["input"].as_str().unwrap();
-            let answer = test["output"].as_str().unwrap();
+fn parse(args: ParseArgs, task: &mut Task) {
+    if args.contest {
+        let requests = listen_companion(args.port, args.echo);
+        receive_contest(&args.count, &requests, |contest_data| {
+            let data = Value::Object(contest_data.clone());
+            fs::write(".preparsed_samples", serde_json::to_string(&data).unwrap()).unwrap();
+        });
+        return;
+    }
 
-            fs::File::create(&["in", &index.to_string()].concat())
-                .unwrap()
-                .write(input.as_bytes())
-                .unwrap();
-            if !answer.is_empty() {
-                fs::File::create(&["ans", &index.to_string()].concat())
-                    .unwrap()
-                    .write(answer.as_bytes())
-                    .unwrap();
+    // without url samples are received from competitive companion
+    let url: Option<String> = if args.force {
+        None
+    } else {
+        match get_url(task) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                eprintln!("No url for the problem ({}), waiting for competitive companion", e);
+                None
             }
         }
-
-        println!("Parsed {} tests", tests.len());
     };
 
-    if args.contest {
-        println!("Creating problems: {:?}", problem_names);
-        url = None;
+    if let Some(ref url) = url {
+        let scraper = get_sources()
+            .into_iter()
+            .find(|x| x.matches_url(url))
+            .and_then(|x| x.scraper);
+        let parsed = match scraper.as_deref() {
+            Some("codeforces") => {
+                parse_codeforces(url, task);
+                true
+            }
+            Some("atcoder") => parse_atcoder(url, task),
+            Some("cses") => parse_cses(url, task),
+            Some("codechef") => parse_codechef(url, task),
+            Some(scraper) => {
+                eprintln!("Unknown scraper \"{}\", waiting for competitive companion", scraper);
+                false
+            }
+            None => false,
+        };
+        if parsed {
+            return;
+        }
     }
 
-    if !args.contest && !args.force {
+    if !args.force {
         let preparsed_samples = fs::read_to_string("../.preparsed_samples");
         if let Ok(preparsed_samples) = preparsed_samples {
             let preparsed: Value = match serde_json::from_str(&preparsed_samples) {
@@ -882,11 +1091,11 @@ fn parse(args: ParseArgs, params: &HashMap<String, String>) {
             let problem = parts[parts.len() - 1];
 
             if let Some(data) = preparsed.get(problem) {
-                create_tests_from_json(data);
+                create_tests_from_json(data, task);
             } else {
                 for suffix in 1.. {
                     if let Some(data) = preparsed.get(&[problem, &suffix.to_string()].concat()) {
-                        create_tests_from_json(data);
+                        create_tests_from_json(data, task);
                     } else {
                         break;
                     }
@@ -904,88 +1113,589 @@ fn parse(args: ParseArgs, params: &HashMap<String, String>) {
         println!("Accepting from any url")
     }
 
-    let listener = TcpListener::bind("127.0.0.1:10046").unwrap();
-
-    let mut problem_iter = 0 as usize;
-    let mut listener_iter = listener.incoming();
-    let mut contest_data: Map<String, Value> = Map::new();
-
-    let mut parsed_problems: HashSet<String> = HashSet::new();
+    let requests = listen_companion(args.port, args.echo);
 
     loop {
-        let mut problem_name: String = String::new();
-        if args.contest {
-            if problem_iter == problem_names.len() {
-                break;
-            } else {
-                problem_name = problem_names[problem_iter].clone();
-                problem_iter += 1;
+        let data = receive_problem(&requests, None).unwrap();
+        let response_url = data["url"].as_str().unwrap().to_string();
+
+        if let Some(ref url) = url {
+            if &response_url != url {
+                println!("Skipping url \"{}\"", response_url);
+                continue;
             }
         }
 
-        let mut stream = listener_iter.next().unwrap().unwrap();
-
-        let mut buffer = [0; 4096];
+        println!("Got url \"{}\"", response_url);
 
-        stream.read(&mut buffer).unwrap();
+        create_tests_from_json(&data, task);
+        return;
+    }
+}
 
-        let response = String::from_utf8_lossy(&buffer[..]);
-        if args.echo {
-            println!("{:?}", response);
-        }
-        let json_start = response.find("\r\n\r\n");
-        if json_start.is_none() {
+/// Waits for the next request from competitive companion with a valid json.
+/// Returns None if nothing came before the deadline
+fn receive_problem(requests: &Receiver<String>, deadline: Option<Instant>) -> Option<Value> {
+    loop {
+        let response = match deadline {
+            Some(deadline) => requests
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok()?,
+            None => requests.recv().unwrap(),
+        };
+        if response.trim().is_empty() {
             println!("Empty response");
-            problem_iter -= 1;
             continue;
         }
-        let response = response[json_start.unwrap() + 4..].to_string();
-        let response = response.trim_matches(char::from(0));
-
-        let data = serde_json::from_str::<Value>(&response);
-        if !data.is_ok() {
-            eprintln!("Can't read json from [{}]", response);
-            problem_iter -= 1;
-            continue;
+        match serde_json::from_str::<Value>(&response) {
+            Ok(x) => return Some(x),
+            Err(_) => eprintln!("Can't read json from [{}]", response),
         }
-        let data = data.unwrap();
+    }
+}
 
-        let response_url = data["url"].as_str().unwrap().to_string();
+/// Receives problems of a contest. If the number of problems is given, names them in the
+/// order of requests, otherwise takes names from the problems and waits until there are no
+/// new ones for a while or Enter is pressed. Calls on_update after each new problem
+fn receive_contest(
+    count: &ProblemCountArgs,
+    requests: &Receiver<String>,
+    mut on_update: impl FnMut(&Map<String, Value>),
+) -> Map<String, Value> {
+    let problem_names = count.problem_names();
+    match problem_names {
+        Some(ref problem_names) => println!("Creating problems: {:?}", problem_names),
+        None => println!("Creating problems with names from urls, press Enter to stop"),
+    }
+    println!("Accepting from any url");
+
+    let (tx_stop, rx_stop): (Sender<()>, Receiver<()>) = mpsc::channel();
+    if problem_names.is_none() {
+        thread::spawn(move || {
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).is_ok() {
+                let _ = tx_stop.send(());
+            }
+        });
+    }
 
-        if let Some(ref url) = url {
-            if &response_url != url {
-                println!("Skipping url \"{}\"", response_url);
-                continue;
+    let mut contest_data: Map<String, Value> = Map::new();
+    let mut parsed_problems: HashSet<String> = HashSet::new();
+    let mut last_update = Instant::now();
+
+    loop {
+        match problem_names {
+            Some(ref problem_names) => {
+                if contest_data.len() == problem_names.len() {
+                    break;
+                }
+            }
+            None => {
+                if rx_stop.try_recv().is_ok() {
+                    break;
+                }
+                if !contest_data.is_empty() && last_update.elapsed().as_secs_f64() > count.wait {
+                    println!("No new problems for {} seconds, stopping", count.wait);
+                    break;
+                }
             }
         }
 
+        let data = match receive_problem(requests, Some(Instant::now() + std::time::Duration::from_millis(200))) {
+            Some(x) => x,
+            None => continue,
+        };
+        let response_url = data["url"].as_str().unwrap().to_string();
+
         println!("Got url \"{}\"", response_url);
 
-        if args.contest {
-            if parsed_problems.contains(&response_url) {
-                println!("duplicate");
-                problem_iter -= 1;
-            } else {
-                contest_data.insert(problem_name, data);
-                parsed_problems.insert(response_url);
-            }
-            let data = Value::Object(contest_data.clone());
-            fs::File::create(".preparsed_samples")
-                .unwrap()
-                .write(serde_json::to_string(&data).unwrap().as_bytes())
-                .unwrap();
-        } else {
-            create_tests_from_json(&data);
-            return;
+        if parsed_problems.contains(&response_url) {
+            println!("duplicate");
+            continue;
         }
+        parsed_problems.insert(response_url);
+
+        let name = match problem_names {
+            Some(ref problem_names) => problem_names[contest_data.len()].clone(),
+            None => {
+                let name = companion_problem_name(&data).unwrap_or((contest_data.len() + 1).to_string());
+                let mut unique_name = name.clone();
+                for suffix in 2.. {
+                    if !contest_data.contains_key(&unique_name) {
+                        break;
+                    }
+                    unique_name = format!("{}_{}", name, suffix);
+                }
+                println!("Problem {}", unique_name);
+                unique_name
+            }
+        };
+        contest_data.insert(name, data);
+        on_update(&contest_data);
+        last_update = Instant::now();
     }
+    contest_data
 }
 
-#[derive(Parser)]
-struct MakeFileArgs {
-    filename: Option<String>,
+/// Problem name from competitive companion data, e.g. "B1" for
+/// https://codeforces.com/contest/1234/problem/B1, "abc123_c" for
+/// https://atcoder.jp/contests/abc123/tasks/abc123_c or "A" for "A. Title"
+fn companion_problem_name(data: &Value) -> Option<String> {
+    let is_name = |token: &str| !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
 
-    /// Use "tstart" template for multitest
+    let url = data["url"].as_str().unwrap_or("").trim_end_matches('/');
+    if url.contains("codeforces.com") || url.contains("atcoder.jp") {
+        if let Some(name) = url.rsplit('/').next().filter(|x| is_name(x)) {
+            return Some(name.to_string());
+        }
+    }
+
+    // "A. Title" on codeforces-like judges, "C - Title" on atcoder-like ones
+    let name = data["name"].as_str()?;
+    let token = name.split(['.', ' ']).next()?;
+    if is_name(token) && token.len() <= 3 {
+        Some(token.to_string())
+    } else {
+        None
+    }
+}
+
+fn create_tests_from_json(data: &Value, task: &mut Task) {
+    let string = |key: &str| data[key].as_str().map(|x| x.to_string());
+    task.name = string("name").or(task.name.take());
+    task.group = string("group").or(task.group.take());
+    task.url = string("url").or(task.url.take());
+    task.test_type = string("testType").or(task.test_type.take());
+    if let Some(time_limit) = data["timeLimit"].as_f64() {
+        task.time_limit = Some(time_limit.round() as u64);
+    }
+    if let Some(memory_limit) = data["memoryLimit"].as_f64() {
+        task.memory_limit = Some(memory_limit.round() as u64);
+    }
+    for (key, file) in [("input", &mut task.input_file), ("output", &mut task.output_file)] {
+        match data[key]["type"].as_str() {
+            Some("file") => {
+                if let Some(name) = data[key]["fileName"].as_str() {
+                    *file = Some(name.to_string());
+                }
+            }
+            Some("regex") => println!("Can't use {} with pattern {}", key, data[key]["pattern"]),
+            _ => {}
+        }
+    }
+    task.save();
+
+    if data["interactive"].as_bool().unwrap() {
+        task.interactive = Some(true);
+        task.save();
+        println!("This is an interactive problem");
+        return;
+    }
+
+    let tests = data["tests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|test| {
+            (
+                test["input"].as_str().unwrap().to_string(),
+                test["output"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    write_tests(&tests);
+
+    println!("Parsed {} tests", tests.len());
+}
+
+/// Accepts competitive companion requests in background, sending their bodies to the channel
+fn listen_companion(port: u16, echo: bool) -> Receiver<String> {
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Can't listen on port {}: {}", port, e);
+            std::process::exit(1);
+        }
+    };
+
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            let tx = tx.clone();
+            thread::spawn(move || {
+                if let Some(body) = read_http_request(stream, echo) {
+                    let _ = tx.send(body);
+                }
+            });
+        }
+    });
+    rx
+}
+
+/// Reads a single HTTP/1.1 request, replies with 200 and returns the body
+fn read_http_request(mut stream: TcpStream, echo: bool) -> Option<String> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+
+    let mut headers: Vec<String> = Vec::new();
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end().to_string();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().ok();
+            }
+        }
+        headers.push(line);
+    }
+
+    let mut body: Vec<u8> = Vec::new();
+    match content_length {
+        Some(length) => {
+            body.resize(length, 0);
+            reader.read_exact(&mut body).ok()?;
+        }
+        None => {
+            reader.read_to_end(&mut body).ok()?;
+        }
+    }
+
+    let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+
+    let body = String::from_utf8_lossy(&body).to_string();
+    if echo {
+        println!("{:?}", [headers.join("\r\n"), body.clone()].join("\r\n\r\n"));
+    }
+    Some(body)
+}
+
+fn parse_codeforces(url: &str, task: &mut Task) {
+    let response = reqwest::blocking::get(url).unwrap().text().unwrap();
+    let soup = Soup::new(&response);
+
+    // newer problems have each line of a sample in a separate <div class="test-example-line">,
+    // older ones use <br> or plain newlines
+    let samples = |class: &str| -> Vec<String> {
+        soup.tag("div")
+            .class(class)
+            .find_all()
+            .filter_map(|x| x.tag("pre").find())
+            .map(|pre| {
+                let lines = pre
+                    .tag("div")
+                    .class("test-example-line")
+                    .find_all()
+                    .map(|line| line.text())
+                    .collect::<Vec<_>>();
+                let text = if !lines.is_empty() {
+                    lines.join("\n")
+                } else {
+                    // reparse html with line breaks as text to get rid of tags and decode entities
+                    let html = pre
+                        .display()
+                        .replace("<br></br>", "\n")
+                        .replace("</br>", "\n")
+                        .replace("<br>", "\n")
+                        .replace("<br/>", "\n")
+                        .replace("<br />", "\n");
+                    Soup::new(&html)
+                        .tag("pre")
+                        .find()
+                        .map(|pre| pre.text())
+                        .unwrap_or_default()
+                };
+                format!("{}\n", text.replace("\r", "").trim_matches('\n'))
+            })
+            .collect()
+    };
+    let inputs = samples("input");
+    let answers = samples("output");
+
+    set_limits(
+        codeforces_property(&soup, "time-limit"),
+        codeforces_property(&soup, "memory-limit"),
+        task,
+    );
+
+    task.tags = soup
+        .tag("span")
+        .class("tag-box")
+        .find_all()
+        .map(|tag| tag.text().trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    task.save();
+
+    let interactive = soup
+        .tag("div")
+        .class("section-title")
+        .find_all()
+        .any(|title| ["Interaction", "Протокол взаимодействия"].contains(&title.text().trim()));
+    if interactive {
+        // samples are still written, they are useful as inputs for the interactor
+        task.interactive = Some(true);
+        task.save();
+        println!("This is an interactive problem");
+    }
+
+    let tests = inputs
+        .into_iter()
+        .enumerate()
+        .map(|(i, input)| (input, answers.get(i).cloned().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    write_tests(&tests);
+
+    println!("Parsed {} tests from codeforces", tests.len());
+}
+
+/// Value of "time-limit" or "memory-limit" block, e.g. 2 from "time limit per test2 seconds"
+fn codeforces_property(soup: &Soup, class: &str) -> Option<f64> {
+    let property = soup.tag("div").class(class).find()?;
+    let title = property
+        .tag("div")
+        .class("property-title")
+        .find()
+        .map(|title| title.text())
+        .unwrap_or_default();
+    let text = property.text();
+    text.strip_prefix(&title)
+        .unwrap_or(&text)
+        .split_whitespace()
+        .next()?
+        .parse::<f64>()
+        .ok()
+}
+
+/// Returns false if the task page is not available, e.g. during a contest without login
+fn parse_atcoder(url: &str, task: &mut Task) -> bool {
+    let client = reqwest::blocking::Client::builder().cookie_store(true).build().unwrap();
+
+    if let Some(auth) = get_settings().auth.get("atcoder") {
+        let login_page = client.get("https://atcoder.jp/login").send().unwrap().text().unwrap();
+        let csrf_token = Soup::new(&login_page)
+            .tag("input")
+            .attr("name", "csrf_token")
+            .find()
+            .and_then(|x| x.get("value"));
+        if let Some(csrf_token) = csrf_token {
+            let response = client
+                .post("https://atcoder.jp/login")
+                .form(&[
+                    ("username", auth.login.as_str()),
+                    ("password", auth.password.as_str()),
+                    ("csrf_token", csrf_token.as_str()),
+                ])
+                .send()
+                .unwrap();
+            if response.url().path() == "/login" {
+                eprintln!("Can't login to atcoder as \"{}\"", auth.login);
+            }
+        } else {
+            eprintln!("Can't find csrf_token on atcoder login page");
+        }
+    }
+
+    let response = client.get(url).send().unwrap();
+    if !response.status().is_success() {
+        println!("Can't get task page from atcoder, status {}", response.status());
+        return false;
+    }
+    let response = response.text().unwrap();
+    let soup = Soup::new(&response);
+
+    // new contests have statement in both languages, old ones only in japanese
+    let statement = soup
+        .tag("span")
+        .class("lang-en")
+        .find()
+        .or_else(|| soup.tag("div").attr("id", "task-statement").find());
+    let statement = match statement {
+        Some(x) => x,
+        None => {
+            println!("Can't find task statement on atcoder page");
+            return false;
+        }
+    };
+
+    let mut inputs: Vec<String> = Vec::new();
+    let mut answers: Vec<String> = Vec::new();
+    for section in statement.tag("section").find_all() {
+        let (title, pre) = match (section.tag("h3").find(), section.tag("pre").find()) {
+            (Some(title), Some(pre)) => (title.text(), pre.text()),
+            _ => continue,
+        };
+        let text = format!("{}\n", pre.replace("\r", "").trim_matches('\n'));
+        if title.starts_with("Sample Input") || title.starts_with("入力例") {
+            inputs.push(text);
+        } else if title.starts_with("Sample Output") || title.starts_with("出力例") {
+            answers.push(text);
+        }
+    }
+
+    // "Time Limit: 2 sec / Memory Limit: 1024 MiB"
+    let limits = soup
+        .tag("p")
+        .find_all()
+        .map(|p| p.text())
+        .find(|text| text.contains("Memory Limit") || text.contains("メモリ制限"));
+    if let Some(limits) = limits {
+        let number = |part: &str| part.split_whitespace().find_map(|token| token.parse::<f64>().ok());
+        if let Some((time_limit, memory_limit)) = limits.split_once('/') {
+            set_limits(number(time_limit), number(memory_limit), task);
+        }
+    }
+
+    if inputs.is_empty() {
+        println!("No samples found on atcoder page");
+        return false;
+    }
+
+    let tests = inputs
+        .into_iter()
+        .enumerate()
+        .map(|(i, input)| (input, answers.get(i).cloned().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    write_tests(&tests);
+
+    println!("Parsed {} tests from atcoder", tests.len());
+    true
+}
+
+fn parse_cses(url: &str, task: &mut Task) -> bool {
+    let response = reqwest::blocking::get(url).unwrap();
+    if !response.status().is_success() {
+        println!("Can't get task page from cses, status {}", response.status());
+        return false;
+    }
+    let response = response.text().unwrap();
+    let soup = Soup::new(&response);
+
+    // "Time limit: 1.00 s", "Memory limit: 512 MB"
+    let constraints = soup
+        .tag("ul")
+        .class("task-constraints")
+        .find()
+        .map(|x| x.tag("li").find_all().map(|li| li.text()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let limit = |name: &str| {
+        constraints.iter().find(|text| text.starts_with(name)).and_then(|text| {
+            text[name.len()..]
+                .split_whitespace()
+                .find_map(|x| x.parse::<f64>().ok())
+        })
+    };
+    set_limits(limit("Time limit:"), limit("Memory limit:"), task);
+
+    // examples are pairs of <pre> after "Input:" and "Output:"
+    let samples = match soup.tag("div").class("md").find() {
+        Some(statement) => statement
+            .tag("pre")
+            .find_all()
+            .map(|pre| format!("{}\n", pre.text().replace("\r", "").trim_matches('\n')))
+            .collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    if samples.is_empty() {
+        println!("No samples found on cses page");
+        return false;
+    }
+
+    let tests = samples
+        .chunks(2)
+        .map(|test| (test[0].clone(), test.get(1).cloned().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    write_tests(&tests);
+
+    println!("Parsed {} tests from cses", tests.len());
+    true
+}
+
+fn parse_codechef(url: &str, task: &mut Task) -> bool {
+    let parts = url.trim_end_matches('/').split('/').collect::<Vec<_>>();
+    let (contest, problem) = match parts.iter().position(|&x| x == "problems") {
+        Some(i) if i > 0 && i + 1 < parts.len() => {
+            let contest = if parts[i - 1].contains("codechef.com") {
+                "PRACTICE"
+            } else {
+                parts[i - 1]
+            };
+            (contest, parts[i + 1])
+        }
+        _ => {
+            println!("Can't get contest and problem from url \"{}\"", url);
+            return false;
+        }
+    };
+
+    let api_url = format!("https://www.codechef.com/api/contests/{}/problems/{}", contest, problem);
+    let data = match reqwest::blocking::get(&api_url).unwrap().json::<Value>() {
+        Ok(x) => x,
+        Err(_) => {
+            println!("Can't read json from \"{}\"", api_url);
+            return false;
+        }
+    };
+
+    let time_limit = match &data["max_timelimit"] {
+        Value::String(x) => x.parse::<f64>().ok(),
+        x => x.as_f64(),
+    };
+    set_limits(time_limit, None, task);
+
+    let tests = data["problemComponents"]["sampleTestCases"]
+        .as_array()
+        .map(|tests| {
+            tests
+                .iter()
+                .filter(|test| !test["isDeleted"].as_bool().unwrap_or(false))
+                .map(|test| {
+                    let text = |key: &str| {
+                        let text = test[key].as_str().unwrap_or("").replace("\r", "");
+                        format!("{}\n", text.trim_matches('\n'))
+                    };
+                    (text("input"), text("output"))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if tests.is_empty() {
+        println!("No samples found for codechef problem {}", problem);
+        return false;
+    }
+    write_tests(&tests);
+
+    println!("Parsed {} tests from codechef", tests.len());
+    true
+}
+
+/// Saves limits to the task, time limit in seconds, memory limit in megabytes
+fn set_limits(time_limit: Option<f64>, memory_limit: Option<f64>, task: &mut Task) {
+    if let Some(time_limit) = time_limit {
+        task.time_limit = Some((time_limit * 1000.).round() as u64);
+    }
+    if let Some(memory_limit) = memory_limit {
+        task.memory_limit = Some(memory_limit.round() as u64);
+    }
+    task.save();
+}
+
+#[derive(clap::Args, Clone)]
+struct TemplateTypeArgs {
+    /// Template type, lines with "[[type:name]]" are kept only for it. "start" by default
+    #[arg(long, conflicts_with_all = ["test", "gen", "gcj"])]
+    template: Option<String>,
+
+    /// Use "tstart" template for multitest
     #[arg(short, long)]
     test: bool,
 
@@ -998,7 +1708,41 @@ struct MakeFileArgs {
     gcj: bool,
 }
 
-fn make_file(args: MakeFileArgs, params: &mut HashMap<String, String>) {
+impl TemplateTypeArgs {
+    fn name(&self) -> String {
+        match (&self.template, self.test, self.gen, self.gcj) {
+            (Some(name), false, false, false) => name.clone(),
+            (None, false, false, false) => "start".to_string(),
+            (None, true, false, false) => "tstart".to_string(),
+            (None, false, true, false) => "gstart".to_string(),
+            (None, false, false, true) => "gcj".to_string(),
+            _ => panic!("At most one of -t, --gen, --gcj, --template must be specified"),
+        }
+    }
+}
+
+#[derive(Parser, Clone)]
+struct MakeFileArgs {
+    filename: Option<String>,
+
+    #[command(flatten)]
+    template_type: TemplateTypeArgs,
+
+    /// Print template types available for each language and exit
+    #[arg(long)]
+    list_templates: bool,
+
+    /// Don't open the file after creation
+    #[arg(long)]
+    no_open: bool,
+}
+
+fn make_file(args: MakeFileArgs, task: &mut Task) {
+    if args.list_templates {
+        list_templates();
+        return;
+    }
+
     let mut filename = args.filename.unwrap_or(String::from(DEFAULT_FILE_NAME));
     let mut extension = get_default_file_extension();
     if let Some((name, ext)) = filename.split_once('.') {
@@ -1006,21 +1750,21 @@ fn make_file(args: MakeFileArgs, params: &mut HashMap<String, String>) {
         filename = name.to_string();
     }
 
-    enum TemplateType {
-        Start,
-        Tstart,
-        Gcj,
-        Gstart,
+    let custom_template = args.template_type.template.is_some();
+    let template_type = args.template_type.name();
+    if let Some(template) = read_template(&format!("start/{}.txt", extension)).filter(|_| custom_template) {
+        let types = template::types(&template);
+        if !types.contains(&template_type) {
+            eprintln!(
+                "Unknown template \"{}\" for .{} files, available: {}",
+                template_type,
+                extension,
+                types.join(", ")
+            );
+            std::process::exit(1);
+        }
     }
 
-    let template_type = match (args.test, args.gen, args.gcj) {
-        (false, false, false) => TemplateType::Start,
-        (true, false, false) => TemplateType::Tstart,
-        (false, true, false) => TemplateType::Gstart,
-        (false, false, true) => TemplateType::Gcj,
-        _ => panic!("At most one of -t, --gen, --gcj must be specified"),
-    };
-
     if extension == "rs" {
         init_rust_directory();
         filename = format!("src/bin/{}", filename);
@@ -1042,66 +1786,27 @@ fn make_file(args: MakeFileArgs, params: &mut HashMap<String, String>) {
 
     let mut file = fs::File::create(&full_name).unwrap();
 
-    let template_type = match template_type {
-        TemplateType::Start => "start",
-        TemplateType::Tstart => "tstart",
-        TemplateType::Gcj => "gcj",
-        TemplateType::Gstart => "gstart",
-    };
-
-    let mut template_path = get_templates_path();
-    template_path.push("start");
-    template_path.push(format!("{}.txt", extension));
+    let template_path = format!("start/{}.txt", extension);
 
     let mut position = (0, 0);
 
-    let template_base = fs::read_to_string(template_path);
-    if let Ok(template_base) = template_base {
+    let template_base = read_template(&template_path);
+    if let Some(template_base) = template_base {
         let template_base = template_base.trim().to_string();
         let template_base = template_base.split('\n').map(|x| x.trim_end()).collect::<Vec<_>>();
 
-        let libs_path = get_settings().config().libs_path.clone();
-
-        let get_filtered_line = |line: &str| -> Option<String> {
-            let mut ind: usize = 0;
-            while ind < line.len() {
-                if !line[ind..].starts_with("[[") {
-                    return Some(line[ind..].to_string());
-                }
-                let next_ind = line[ind..].find("]]").unwrap() + ind;
-                let filter = line[ind + 2..next_ind].split(':').collect::<Vec<_>>();
-                let filter_type = filter[0];
-                let values = filter[1].split('|').collect::<Vec<_>>();
-
-                let good_line = match filter_type {
-                    "os" => values.contains(&env::consts::OS),
-                    "type" => values.contains(&template_type),
-                    _ => false,
-                };
-                if !good_line {
-                    return None;
-                }
-                ind = next_ind + 2;
+        let vars = template_vars(task, &template_type, &extension);
+        let mut template = match template::render(&template_base.join("\n"), &vars) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("Can't render template {}: {}", template_path, e);
+                std::process::exit(1);
             }
-            return Some("".to_string());
         };
 
-        let mut template = String::new();
-
-        for line in template_base.iter() {
-            if let Some(mut line) = get_filtered_line(line) {
-                if let Some(libs_path) = libs_path.as_ref() {
-                    line = line.replace("[libs_path]", libs_path);
-                }
-                template += &line;
-                template.push('\n');
-            }
-        }
-
-        let now = Local::now();
         template = template
             .replace("\\$", "$")
-            .replace("${1:date}", &now.format("%d.%m.%Y %H:%M:%S").to_string())
+            .replace("${1:date}", &vars["date"])
             .to_string();
 
         let mut cursor_expr = "$0";
@@ -1124,11 +1829,13 @@ fn make_file(args: MakeFileArgs, params: &mut HashMap<String, String>) {
         file.write(&template.as_bytes()).unwrap();
     }
 
-    if !params.contains_key("main") {
-        add_param("main", full_name, params);
+    if task.main.is_none() {
+        task.main = Some(full_name.clone());
+        task.lang = Some(extension.clone());
+        task.save();
     }
 
-    if OPEN_FILE_ON_CREATION {
+    if OPEN_FILE_ON_CREATION && !args.no_open {
         if let Some(open_file_cmd) = get_settings().config().open_file_cmd.as_ref().map(|s| s.as_str()) {
             let open_file_cmd = open_file_cmd
                 .replace("[file]", full_name)
@@ -1144,9 +1851,133 @@ fn make_file(args: MakeFileArgs, params: &mut HashMap<String, String>) {
     }
 }
 
-fn init_task(args: MakeFileArgs, params: &mut HashMap<String, String>) {
-    make_file(args, params);
-    parse(ParseArgs::default(), params);
+/// Prints template types from "[[type:...]]" filters in "start/*.txt" templates
+fn list_templates() {
+    for (extension, template) in template::start_templates(get_templates_path().as_deref()) {
+        let types = template::types(&template);
+        println!("{}: {}", extension, types.join(", "));
+    }
+}
+
+/// Variables for templates: problem info from the task, "author", "libs_path" and "vars"
+/// from config, and "os", "type", "lang", "date" of the file
+fn template_vars(task: &Task, template_type: &str, extension: &str) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    let mut set = |name: &str, value: Option<String>| {
+        if let Some(value) = value {
+            vars.insert(name.to_string(), value);
+        }
+    };
+
+    let folder = |level: usize| {
+        let dir = std::env::current_dir().ok()?;
+        let dir = dir.ancestors().nth(level)?;
+        dir.file_name()?.to_str().map(|x| x.to_string())
+    };
+    let url = task.url.clone().or(guess_url_from_path().ok());
+    let (contest, problem) = match url.as_ref() {
+        Some(url) => match (submit::CodeforcesProblem::from_url(url), submit::atcoder_task(url)) {
+            (Some(x), _) => (Some(x.contest), Some(x.index)),
+            (_, Some((contest, _))) => (Some(contest), folder(0)),
+            _ => (folder(1), folder(0)),
+        },
+        None => (folder(1), folder(0)),
+    };
+
+    set("name", task.name.clone());
+    set("group", task.group.clone());
+    set("url", url);
+    set("contest", contest);
+    set("problem", problem);
+    set("time_limit", task.time_limit.map(|x| x.to_string()));
+    set("memory_limit", task.memory_limit.map(|x| x.to_string()));
+    // not set for other problems, so that "[[if !interactive]]" works
+    set("interactive", task.is_interactive().then(|| "true".to_string()));
+    set("input_file", task.input_file.clone());
+    set("output_file", task.output_file.clone());
+    set("os", Some(env::consts::OS.to_string()));
+    set("type", Some(template_type.to_string()));
+    set("lang", Some(extension.to_string()));
+    set("date", Some(Local::now().format("%d.%m.%Y %H:%M:%S").to_string()));
+
+    if let Some(config) = read_settings().and_then(|x| x.config.get(x.active_profile()).cloned()) {
+        set("author", config.author.clone());
+        set("libs_path", config.libs_path.clone());
+        for (name, value) in config.vars.iter() {
+            set(name, Some(value.clone()));
+        }
+    }
+    vars
+}
+
+fn init_task(args: MakeFileArgs, task: &mut Task) {
+    make_file(args, task);
+    // through clap for the default port and wait time
+    parse(ParseArgs::parse_from(["parse"]), task);
+}
+
+#[derive(Subcommand)]
+enum ContestOptions {
+    /// Receive all problems from competitive companion and create a folder for each of them
+    ///
+    /// Each folder gets a file with template (see "cpr mk --help"), tests and task.json.
+    /// Problem names are taken from urls, unless one of --nA, --na, --n1 is specified
+    Init(ContestInitArgs),
+}
+
+#[derive(Parser)]
+struct ContestArgs {
+    #[command(subcommand)]
+    option: ContestOptions,
+}
+
+#[derive(Parser)]
+struct ContestInitArgs {
+    #[command(flatten)]
+    count: ProblemCountArgs,
+
+    #[command(flatten)]
+    template_type: TemplateTypeArgs,
+
+    /// Print full responses
+    #[arg(long)]
+    echo: bool,
+
+    /// Port to listen on for competitive companion
+    #[arg(long, default_value_t = COMPANION_PORT)]
+    port: u16,
+}
+
+fn contest(args: ContestArgs, task: &Task) {
+    match args.option {
+        ContestOptions::Init(args) => contest_init(args, task),
+    }
+}
+
+fn contest_init(args: ContestInitArgs, _task: &Task) {
+    let requests = listen_companion(args.port, args.echo);
+    let contest_data = receive_contest(&args.count, &requests, |_| {});
+
+    let contest_dir = std::env::current_dir().unwrap();
+    for (name, data) in contest_data.iter() {
+        println!("Problem {}:", name);
+        fs::create_dir_all(name).unwrap();
+        std::env::set_current_dir(contest_dir.join(name)).unwrap();
+
+        let mut task = Task::load().with_defaults(task_defaults());
+        create_tests_from_json(data, &mut task);
+        make_file(
+            MakeFileArgs {
+                filename: None,
+                template_type: args.template_type.clone(),
+                list_templates: false,
+                no_open: true,
+            },
+            &mut task,
+        );
+
+        std::env::set_current_dir(&contest_dir).unwrap();
+    }
 }
 
 #[derive(Parser)]
@@ -1159,7 +1990,7 @@ struct MakeTestArgs {
     from_zero: bool,
 }
 
-fn make_test(args: MakeTestArgs, _params: &HashMap<String, String>) {
+fn make_test(args: MakeTestArgs, _task: &Task) {
     let index = args.index.unwrap_or(first_available_test());
 
     if args.from_zero {
@@ -1221,7 +2052,7 @@ struct SplitTestArgs {
     input: String,
 }
 
-fn split_test(args: SplitTestArgs, _params: &HashMap<String, String>) {
+fn split_test(args: SplitTestArgs, _task: &Task) {
     let filename = args.filename;
     let input = args.input;
 
@@ -1317,6 +2148,129 @@ fn split_test(args: SplitTestArgs, _params: &HashMap<String, String>) {
     println!("\rCreated {} tests", split_positions.len() - 2);
 }
 
+#[derive(Parser)]
+struct FetchTestsArgs {
+    /// Polygon package (*.zip or unpacked folder), or a folder with tests.
+    /// Can also be a url to download the package from
+    path: String,
+
+    /// Don't copy and compile the checker from the package
+    #[arg(long)]
+    no_checker: bool,
+}
+
+fn fetch_tests(args: FetchTestsArgs, task: &mut Task) {
+    let mut path = args.path.clone();
+    let downloaded = path.starts_with("http://") || path.starts_with("https://");
+    if downloaded {
+        print!("Downloading...");
+        io::stdout().flush().unwrap();
+        let response = match reqwest::blocking::get(&path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("\rCan't download package: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if !response.status().is_success() {
+            eprintln!("\rCan't download package, status {}", response.status());
+            std::process::exit(1);
+        }
+        fs::write(PACKAGE_ZIP, response.bytes().unwrap()).unwrap();
+        println!("\rDownloaded package");
+        path = PACKAGE_ZIP.to_string();
+    }
+
+    let is_zip = path.ends_with(".zip");
+    let result = import_package(&path, is_zip, args.no_checker, task);
+    // the downloaded and unpacked packages are needed only for the import
+    if is_zip && Path::new(PACKAGE_DIR).exists() {
+        fs::remove_dir_all(PACKAGE_DIR).unwrap();
+    }
+    if downloaded {
+        fs::remove_file(PACKAGE_ZIP).unwrap();
+    }
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Copies tests and the checker from the package, a zip is unpacked into PACKAGE_DIR first
+fn import_package(path: &str, is_zip: bool, no_checker: bool, task: &mut Task) -> Result<(), String> {
+    let package = if is_zip {
+        if Path::new(PACKAGE_DIR).exists() {
+            fs::remove_dir_all(PACKAGE_DIR).unwrap();
+        }
+        fs::create_dir_all(PACKAGE_DIR).unwrap();
+        let cmd = if cfg!(windows) {
+            vec!["tar", "-xf", path, "-C", PACKAGE_DIR]
+        } else {
+            vec!["unzip", "-q", path, "-d", PACKAGE_DIR]
+        };
+        let result = Popen::create(&cmd, PopenConfig { ..Default::default() }).and_then(|mut p| p.wait());
+        if !result.is_ok_and(|x| x.success()) {
+            return Err(format!("Can't unpack \"{}\" with {:?}", path, cmd));
+        }
+        Path::new(PACKAGE_DIR).to_path_buf()
+    } else {
+        Path::new(path).to_path_buf()
+    };
+
+    // polygon has tests "tests/01" with answers "tests/01.a"
+    let tests_dir = if package.join("tests").is_dir() {
+        package.join("tests")
+    } else {
+        package.clone()
+    };
+    let mut tests: Vec<(i32, std::path::PathBuf)> = fs::read_dir(&tests_dir)
+        .map_err(|e| format!("Can't read tests from {}: {}", tests_dir.display(), e))?
+        .map(|x| x.unwrap().path())
+        .filter_map(|x| {
+            let index = x.file_name().unwrap().to_str().unwrap().parse::<i32>().ok()?;
+            Some((index, x))
+        })
+        .collect();
+    tests.sort();
+
+    let mut answers = 0;
+    for (index, input) in tests.iter() {
+        fs::copy(input, format!("in{}", index)).unwrap();
+        let answer = input.with_file_name(format!("{}.a", input.file_name().unwrap().to_str().unwrap()));
+        if answer.exists() {
+            fs::copy(answer, format!("ans{}", index)).unwrap();
+            answers += 1;
+        } else if Path::new(&format!("ans{}", index)).exists() {
+            // the answer of a sample with the same index doesn't belong to this test
+            fs::remove_file(format!("ans{}", index)).unwrap();
+        }
+    }
+    println!("Imported {} tests, {} with answers", tests.len(), answers);
+
+    if !no_checker {
+        let checker = [package.join("check.cpp"), package.join("files").join("check.cpp")]
+            .into_iter()
+            .find(|x| x.exists());
+        if let Some(checker) = checker {
+            fs::copy(checker, "check.cpp").unwrap();
+            for testlib in [package.join("testlib.h"), package.join("files").join("testlib.h")] {
+                if testlib.exists() {
+                    fs::copy(testlib, "testlib.h").unwrap();
+                    break;
+                }
+            }
+            if compile_cpp("check", "").is_ok() {
+                task.checker = Some(Checker::Testlib);
+                task.save();
+                println!("Compiled checker from the package into \"check\"");
+            } else {
+                eprintln!("Can't compile checker from the package");
+            }
+        }
+    }
+    Ok(())
+}
+
 #[derive(Parser)]
 struct MultirunArgs {
     /// Main executable to run
@@ -1331,7 +2285,7 @@ struct MultirunArgs {
     output: Option<String>,
 }
 
-fn multirun(args: MultirunArgs, _params: &HashMap<String, String>) {
+fn multirun(args: MultirunArgs, _task: &Task) {
     let mut filename = args.filename.unwrap_or(String::from(DEFAULT_FILE_NAME));
 
     filename = [filename, String::from("."), get_default_file_extension()].concat();
@@ -1399,123 +2353,485 @@ fn multirun(args: MultirunArgs, _params: &HashMap<String, String>) {
 
     let failed_tests = Arc::new(Mutex::new(0));
 
-    let pool = ThreadPool::new(args.threads);
+    let pool = ThreadPool::new(args.threads);
+
+    for input in tests.iter() {
+        let input = input.clone();
+        let local_result_string = result_string_mutex.clone();
+        let local_failed_tests = failed_tests.clone();
+
+        pool.execute(move || {
+            let test_num = input.parse::<usize>().unwrap();
+
+            local_result_string.lock().unwrap()[test_num] = b'.';
+            print!(
+                "\r{}",
+                String::from_utf8(local_result_string.lock().unwrap().to_vec()).unwrap()
+            );
+            io::stdout().flush().unwrap();
+
+            let result = run_and_wait(
+                &[&fix_unix_filename("cpr_tmp_file"), &input],
+                &format!("tests/{}", input),
+                &format!("tests/{}_out", input),
+                None,
+            );
+
+            if result.success() {
+                local_result_string.lock().unwrap()[test_num] = b'+';
+            } else {
+                local_result_string.lock().unwrap()[test_num] = b'X';
+                *local_failed_tests.lock().unwrap() += 1;
+            }
+            print!(
+                "\r{}",
+                String::from_utf8(local_result_string.lock().unwrap().to_vec()).unwrap()
+            );
+            io::stdout().flush().unwrap();
+        });
+    }
+
+    pool.join();
+    println!("");
+
+    if *failed_tests.lock().unwrap() != 0 {
+        let mut stdout = StandardStream::stdout(ColorChoice::Always);
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
+        writeln!(&mut stdout, "failed {} tests", failed_tests.lock().unwrap()).unwrap();
+        stdout.set_color(&ColorSpec::new()).unwrap();
+    }
+
+    if let Some(output) = args.output {
+        let mut tests = tests;
+        tests.sort();
+        let mut res = String::new();
+        for input in tests.iter() {
+            res += &fs::read_to_string(&format!("tests/{}_out", input)).unwrap().to_string();
+        }
+        let mut file = fs::File::create(output).unwrap();
+        file.write(&res.as_bytes()).unwrap();
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigOptions {
+    /// Print the value of a key
+    Get(ConfigKeyArgs),
+
+    /// Set the value of a key
+    Set(ConfigSetArgs),
+
+    /// Remove the value of a key, so that the default is used
+    Unset(ConfigKeyArgs),
+
+    /// Print all values
+    List,
+}
+
+#[derive(Parser)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    option: ConfigOptions,
+
+    /// Profile to use, the current one by default
+    #[arg(long, global = true)]
+    profile: Option<String>,
+}
+
+#[derive(Parser)]
+struct ConfigKeyArgs {
+    /// Dotted key, e.g. "lang" or "build.release.cmd". Use "\\." for a dot inside a key
+    key: String,
+}
+
+#[derive(Parser)]
+struct ConfigSetArgs {
+    /// Dotted key, e.g. "lang" or "build.release.cmd". Use "\\." for a dot inside a key
+    key: String,
+
+    /// Value as json, e.g. '["g++", "[filename].cpp"]', or a plain string
+    value: String,
+}
+
+fn config(args: ConfigArgs, _task: &Task) {
+    let mut settings: Settings = load_settings();
+    let profile = args.profile.unwrap_or(settings.active_profile().to_string());
+    let config = match settings.config.get(&profile) {
+        Some(x) => x,
+        None => {
+            eprintln!(
+                "No such profile {}, available: {:?}",
+                profile,
+                settings.config.keys().collect::<Vec<_>>()
+            );
+            std::process::exit(1);
+        }
+    };
+    let mut value = serde_json::to_value(config).unwrap();
+
+    match args.option {
+        ConfigOptions::Get(args) => {
+            let path = split_config_key(&args.key);
+            match path.iter().try_fold(&value, |value, key| value.get(key)) {
+                Some(Value::String(x)) => println!("{}", x),
+                Some(Value::Null) | None => {
+                    eprintln!("\"{}\" is not set", args.key);
+                    std::process::exit(1);
+                }
+                Some(x) => println!("{}", serde_json::to_string_pretty(x).unwrap()),
+            }
+        }
+        ConfigOptions::List => {
+            let mut lines = Vec::new();
+            flatten_config(&value, "", &mut lines);
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+        ConfigOptions::Set(ConfigSetArgs { key, value: new_value }) => {
+            let path = split_config_key(&key);
+            // "89" is parsed as a number, but it's a string for keys like "submit_lang.codeforces"
+            let mut candidates = vec![Value::String(new_value.clone())];
+            if let Ok(parsed) = serde_json::from_str::<Value>(&new_value) {
+                candidates.insert(0, parsed);
+            }
+            let mut results = candidates.into_iter().map(|new_value| {
+                let mut value = value.clone();
+                set_config_value(&mut value, &path, new_value.clone());
+                (new_value, serde_json::from_value::<Config>(value))
+            });
+            let first = results.next().unwrap();
+            // the error of the first candidate is more relevant, if none of them fit
+            let (new_value, config) = match first.1 {
+                Ok(_) => first,
+                Err(_) => results.find(|x| x.1.is_ok()).unwrap_or(first),
+            };
+            let config = match config {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Invalid value for \"{}\": {}", key, e);
+                    std::process::exit(1);
+                }
+            };
+            // unknown keys are dropped when parsing, so check that the value is still there
+            let saved = serde_json::to_value(&config).unwrap();
+            if path.iter().try_fold(&saved, |value, key| value.get(key)) != Some(&new_value) {
+                eprintln!("Unknown key \"{}\"", key);
+                std::process::exit(1);
+            }
+            settings.config.insert(profile, config);
+            write_settings(&settings);
+        }
+        ConfigOptions::Unset(args) => {
+            let path = split_config_key(&args.key);
+            let parent = path[..path.len() - 1]
+                .iter()
+                .try_fold(&mut value, |value, key| value.get_mut(key))
+                .and_then(|x| x.as_object_mut());
+            if parent.and_then(|x| x.remove(path.last().unwrap())).is_none() {
+                eprintln!("\"{}\" is not set", args.key);
+                std::process::exit(1);
+            }
+            let config = parse_config(value, &args.key);
+            settings.config.insert(profile, config);
+            write_settings(&settings);
+        }
+    }
+}
+
+/// Sets the value at path, creating objects on the way
+fn set_config_value(value: &mut Value, path: &[String], new_value: Value) {
+    let mut current = value;
+    for part in path[..path.len() - 1].iter() {
+        if !current.get(part).is_some_and(|x| x.is_object()) {
+            current[part] = Value::Object(serde_json::Map::new());
+        }
+        current = current.get_mut(part).unwrap();
+    }
+    current[path.last().unwrap()] = new_value;
+}
+
+/// "build.release.cmd" -> ["build", "release", "cmd"], ".vscode.a\\.b" -> [".vscode", "a.b"]
+fn split_config_key(key: &str) -> Vec<String> {
+    let mut res = vec![String::new()];
+    let mut chars = key.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&'.') {
+            res.last_mut().unwrap().push(chars.next().unwrap());
+        } else if c == '.' && !res.last().unwrap().is_empty() {
+            res.push(String::new());
+        } else {
+            res.last_mut().unwrap().push(c);
+        }
+    }
+    res
+}
+
+/// Lines "key = value" for all values that are not objects
+fn flatten_config(value: &Value, prefix: &str, lines: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter() {
+                // a leading dot is a part of the key anyway, like in ".vscode"
+                let key = match key.strip_prefix('.') {
+                    Some(rest) => format!(".{}", rest.replace('.', "\\.")),
+                    None => key.replace('.', "\\."),
+                };
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_config(value, &key, lines);
+            }
+        }
+        Value::Null => {}
+        _ => lines.push(format!("{} = {}", prefix, value)),
+    }
+}
 
-    for input in tests.iter() {
-        let input = input.clone();
-        let local_result_string = result_string_mutex.clone();
-        let local_failed_tests = failed_tests.clone();
+fn parse_config(value: Value, key: &str) -> Config {
+    match serde_json::from_value::<Config>(value) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Invalid value for \"{}\": {}", key, e);
+            std::process::exit(1);
+        }
+    }
+}
 
-        pool.execute(move || {
-            let test_num = input.parse::<usize>().unwrap();
+#[derive(Subcommand)]
+enum ProfileOptions {
+    /// List all profiles, the current one is marked with "*"
+    List,
 
-            local_result_string.lock().unwrap()[test_num] = b'.';
-            print!(
-                "\r{}",
-                String::from_utf8(local_result_string.lock().unwrap().to_vec()).unwrap()
-            );
-            io::stdout().flush().unwrap();
+    /// Create a new profile
+    New(ProfileNewArgs),
 
-            let result = run_and_wait(
-                &[&fix_unix_filename("cpr_tmp_file"), &input],
-                &format!("tests/{}", input),
-                &format!("tests/{}_out", input),
-                None,
-            );
+    /// Remove a profile
+    Rm(ProfileRmArgs),
+}
 
-            if result.success() {
-                local_result_string.lock().unwrap()[test_num] = b'+';
-            } else {
-                local_result_string.lock().unwrap()[test_num] = b'X';
-                *local_failed_tests.lock().unwrap() += 1;
-            }
-            print!(
-                "\r{}",
-                String::from_utf8(local_result_string.lock().unwrap().to_vec()).unwrap()
-            );
-            io::stdout().flush().unwrap();
-        });
-    }
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct ProfileArgs {
+    #[command(subcommand)]
+    option: Option<ProfileOptions>,
 
-    pool.join();
-    println!("");
+    /// Profile to switch to
+    profile: Option<String>,
+}
 
-    if *failed_tests.lock().unwrap() != 0 {
-        let mut stdout = StandardStream::stdout(ColorChoice::Always);
-        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
-        writeln!(&mut stdout, "failed {} tests", failed_tests.lock().unwrap()).unwrap();
-        stdout.set_color(&ColorSpec::new()).unwrap();
-    }
+#[derive(Parser)]
+struct ProfileNewArgs {
+    name: String,
 
-    if let Some(output) = args.output {
-        let mut tests = tests;
-        tests.sort();
-        let mut res = String::new();
-        for input in tests.iter() {
-            res += &fs::read_to_string(&format!("tests/{}_out", input)).unwrap().to_string();
-        }
-        let mut file = fs::File::create(output).unwrap();
-        file.write(&res.as_bytes()).unwrap();
-    }
+    /// Copy config from this profile instead of starting with an empty one
+    #[arg(long)]
+    from: Option<String>,
 }
 
 #[derive(Parser)]
-struct ConfigArgs {
+struct ProfileRmArgs {
     name: String,
-    value: String,
 }
 
-fn config(args: ConfigArgs, _params: &HashMap<String, String>) {
-    let mut settings: Settings = get_settings();
+fn profile(args: ProfileArgs, _task: &Task) {
+    let mut settings: Settings = load_settings();
+    let check_exists = |settings: &Settings, profile: &str| {
+        if !settings.config.contains_key(profile) {
+            eprintln!(
+                "No such profile {}, available: {:?}",
+                profile,
+                settings.config.keys().collect::<Vec<_>>()
+            );
+            std::process::exit(1);
+        }
+    };
 
-    if &args.name == "lang" {
-        settings.config_mut().lang = Some(args.value);
-    } else if &args.name == "open_file_cmd" {
-        settings.config_mut().open_file_cmd = Some(args.value);
-    } else {
-        eprintln!("Unknown param_name [{}]", &args.name);
-        std::process::exit(1);
+    match (args.option, args.profile) {
+        (Some(ProfileOptions::List), _) | (None, None) => {
+            for name in settings.config.keys() {
+                let mark = if name == &settings.profile { "*" } else { " " };
+                if Some(name) == PROFILE_OVERRIDE.get() && name != &settings.profile {
+                    println!("{} {} (for this folder)", mark, name);
+                } else {
+                    println!("{} {}", mark, name);
+                }
+            }
+        }
+        (Some(ProfileOptions::New(args)), _) => {
+            if settings.config.contains_key(&args.name) {
+                eprintln!("Profile {} already exists", args.name);
+                std::process::exit(1);
+            }
+            let config = match args.from {
+                Some(from) => {
+                    check_exists(&settings, &from);
+                    settings.config[&from].clone()
+                }
+                None => Config::default(),
+            };
+            settings.config.insert(args.name, config);
+            write_settings(&settings);
+        }
+        (Some(ProfileOptions::Rm(args)), _) => {
+            check_exists(&settings, &args.name);
+            if args.name == settings.profile {
+                eprintln!("Can't remove the current profile, switch to another one first");
+                std::process::exit(1);
+            }
+            settings.config.remove(&args.name);
+            write_settings(&settings);
+        }
+        (None, Some(profile)) => {
+            check_exists(&settings, &profile);
+            settings.profile = profile;
+            write_settings(&settings);
+        }
     }
-
-    std::fs::create_dir_all(Path::new(SETTINGS_FILE).parent().unwrap()).unwrap();
-    fs::write(SETTINGS_FILE, serde_json::to_string_pretty(&settings).unwrap()).unwrap();
 }
 
 #[derive(Parser)]
-struct ProfileArgs {
-    profile: String,
+struct SetupArgs {
+    /// Use default answers for all questions
+    #[arg(short, long)]
+    yes: bool,
 }
 
-fn profile(args: ProfileArgs, _params: &HashMap<String, String>) {
-    let mut settings: Settings = get_settings();
-    let profile = args.profile;
-    if !settings.config.contains_key(&profile) {
-        panic!(
-            "No such profile {}, available: {:?}",
-            profile,
-            settings.config.keys().collect::<Vec<_>>()
-        );
+fn setup(args: SetupArgs, _task: &Task) {
+    let path = settings_file();
+    let mut settings = match fs::read_to_string(&path) {
+        Ok(data) => match serde_json::from_str::<Settings>(&data) {
+            Ok(x) => x,
+            Err(e) => {
+                // overwriting it would lose the profiles and "auth"
+                eprintln!(
+                    "Can't parse json from {}: {}, fix or remove it first",
+                    path.display(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        },
+        Err(_) => Settings::default(),
+    };
+    println!("Settings file: {}", path.display());
+
+    let ask = |question: &str, default: &str| -> String {
+        if args.yes {
+            return default.to_string();
+        }
+        print!("{} [{}]: ", question, default);
+        io::stdout().flush().unwrap();
+        let mut ans = String::new();
+        io::stdin().read_line(&mut ans).unwrap();
+        let ans = ans.trim();
+        if ans.is_empty() {
+            default.to_string()
+        } else {
+            ans.to_string()
+        }
+    };
+
+    let lang = ask("Language (file extension)", DEFAULT_FILE_EXTENSION);
+    let profile = ask("Profile name", &lang);
+    if settings.config.contains_key(&profile) && ask("Profile already exists. Overwrite? (y/n)", "n") != "y" {
+        println!("Cancelling");
+        std::process::exit(1);
     }
+    let open_file_cmd = ask("Command to open files, \"-\" for none", "code -g [file]:[line]:[char]");
 
-    settings.profile = profile;
+    let build_cmd = |cmd: &[&str]| BuildProfile {
+        cmd: cmd.iter().map(|x| x.to_string()).collect(),
+    };
+    let build = match lang.as_str() {
+        "cpp" => BTreeMap::from([
+            (
+                "debug".to_string(),
+                build_cmd(&[
+                    "g++",
+                    "--std=c++20",
+                    "-DHOME",
+                    "-DHOUSE",
+                    "-g",
+                    "-fsanitize=address,undefined",
+                    "[filename].[extension]",
+                    "-o",
+                    "[filename]",
+                ]),
+            ),
+            (
+                "release".to_string(),
+                build_cmd(&[
+                    "g++",
+                    "--std=c++20",
+                    "-DHOUSE",
+                    "-O2",
+                    "[filename].[extension]",
+                    "-o",
+                    "[filename]",
+                ]),
+            ),
+            (
+                "gdb".to_string(),
+                build_cmd(&[
+                    "g++",
+                    "--std=c++20",
+                    "-DHOME",
+                    "-DHOUSE",
+                    "-g",
+                    "-O0",
+                    "[filename].[extension]",
+                    "-o",
+                    "[filename]",
+                ]),
+            ),
+        ]),
+        "rs" => BTreeMap::from([
+            (
+                "debug".to_string(),
+                build_cmd(&["cargo", "build", "--bin", "[filename]"]),
+            ),
+            (
+                "release".to_string(),
+                build_cmd(&["cargo", "build", "--release", "--bin", "[filename]"]),
+            ),
+            ("gdb".to_string(), build_cmd(&["cargo", "build", "--bin", "[filename]"])),
+        ]),
+        _ => BTreeMap::new(),
+    };
 
-    std::fs::create_dir_all(Path::new(SETTINGS_FILE).parent().unwrap()).unwrap();
-    fs::write(SETTINGS_FILE, serde_json::to_string_pretty(&settings).unwrap()).unwrap();
+    settings.config.insert(
+        profile.clone(),
+        Config {
+            lang: Some(lang),
+            open_file_cmd: if open_file_cmd == "-" {
+                None
+            } else {
+                Some(open_file_cmd)
+            },
+            build,
+            ..Default::default()
+        },
+    );
+    settings.profile = profile.clone();
+    write_settings(&settings);
+    println!("Created profile \"{}\", it's the current one now", profile);
 }
 
 #[derive(Parser)]
 struct WorkspaceArgs {}
 
-fn workspace(_args: WorkspaceArgs, _params: &HashMap<String, String>) {
+fn workspace(_args: WorkspaceArgs, _task: &Task) {
     let settings: Settings = get_settings();
     let config = settings.config();
     let lang = config.lang.as_ref().unwrap();
 
     match lang.as_str() {
         "cpp" => {
-            let compile_flags = get_templates_path().join("cpp_compile_flags.txt");
-            if fs::exists(&compile_flags).unwrap() {
-                fs::copy(&compile_flags, "compile_flags.txt").unwrap();
+            if let Some(compile_flags) = read_template("cpp_compile_flags.txt") {
+                fs::write("compile_flags.txt", compile_flags).unwrap();
             }
         }
         "rs" => {
@@ -1524,7 +2840,7 @@ fn workspace(_args: WorkspaceArgs, _params: &HashMap<String, String>) {
                 panic!("Cargo.toml already exists here");
             }
 
-            let lines = fs::read_to_string(get_templates_path().join("Cargo_workspace.toml")).unwrap();
+            let lines = read_template("Cargo_workspace.toml").unwrap();
             let mut file = fs::File::create(cargo_path).unwrap();
             file.write_all(lines.as_bytes()).unwrap();
 
@@ -1558,20 +2874,17 @@ struct BuildAliasArgs {
     filename: Option<String>,
 }
 
-fn build(args: BuildArgs, _params: &HashMap<String, String>) {
+fn build(args: BuildArgs, task: &Task) {
     let settings = get_settings();
-    let config = settings.config();
-    let profile = config
-        .build
+    // profiles from task.json of the problem or its parent folders override the ones from settings
+    let mut profiles = settings.config().build.clone();
+    profiles.extend(task.build.clone());
+    let profile = profiles
         .get(&args.profile)
         .or_else(|| {
-            let cnt = config.build.keys().filter(|k| k.starts_with(&args.profile)).count();
+            let cnt = profiles.keys().filter(|k| k.starts_with(&args.profile)).count();
             if cnt == 1 {
-                config
-                    .build
-                    .iter()
-                    .find(|p| p.0.starts_with(&args.profile))
-                    .map(|p| p.1)
+                profiles.iter().find(|p| p.0.starts_with(&args.profile)).map(|p| p.1)
             } else {
                 None
             }
@@ -1631,12 +2944,13 @@ enum Args {
     /// Stress test for interactive problems
     Interact(InteractArgs),
 
-    /// Parses samples using competitive companion (port 10046)
+    /// Parses samples using competitive companion (port 10046 by default)
     Parse(ParseArgs),
 
     /// Creates file with template
     ///
-    /// Default filename is "main"
+    /// Default filename is "main". Use "--template name" to keep lines for this type
+    /// of template, see "cpr mk --list-templates" for available ones.
     #[command(name = "mk")]
     MakeFile(MakeFileArgs),
 
@@ -1666,6 +2980,13 @@ enum Args {
     #[command(name = "splittest")]
     SplitTest(SplitTestArgs),
 
+    /// Import tests from a polygon package
+    ///
+    /// Copies "tests/01", "tests/01.a", ... to "in1", "ans1", ... and compiles the
+    /// checker from the package into "check". Tests are then checked with it in "cpr test".
+    #[command(name = "fetch-tests")]
+    FetchTests(FetchTestsArgs),
+
     /// Runs the solution on tests using multiple threads. First run "splittest"
     Multirun(MultirunArgs),
 
@@ -1676,10 +2997,52 @@ enum Args {
     /// best one. In the end calls "finalize".
     Approx(ApproxArgs),
 
-    /// Update config value
+    /// Submit solution to codeforces and wait for the verdict
+    ///
+    /// Uses "url" of the task, credentials from "auth" in settings and language id
+    /// from "submit_lang" in the current profile.
+    Submit(SubmitArgs),
+
+    /// Bundle the solution into a single file for submitting
+    ///
+    /// Local "#include"s are expanded for C++ and used crates from Cargo.toml
+    /// (e.g. rlib) are inlined as modules for Rust.
+    Bundle(BundleArgs),
+
+    /// List and add snippets from "libs_path"
+    ///
+    /// C++ headers are included at the "// cpr lib" line of the solution, Rust
+    /// crates are added to Cargo.toml.
+    Lib(LibArgs),
+
+    /// Show verdicts of the latest submissions for the problem
+    ///
+    /// Works for codeforces and atcoder, refreshes until all verdicts are final.
+    Status(StatusArgs),
+
+    /// Contest commands
+    ///
+    /// Use "cpr contest init --help" for more info.
+    Contest(ContestArgs),
+
+    /// Get, set or list config values
+    ///
+    /// Works with any field of the profile config, use dotted keys like
+    /// "build.release.cmd". Values are parsed as json if possible.
     Config(ConfigArgs),
 
+    /// Create settings with a profile for your language
+    ///
+    /// Asks for the language, command to open files and creates build profiles
+    /// "debug", "release" and "gdb" for it. Settings are stored in
+    /// "$XDG_CONFIG_HOME/cpr/settings.json" ("%APPDATA%\\cpr\\settings.json" on windows),
+    /// set CPR_CONFIG to use another file.
+    Setup(SetupArgs),
+
     /// Change language profile
+    ///
+    /// "cpr profile [name]" switches to the profile, "list", "new" and "rm" manage them.
+    /// Set "profile" in task.json to use another profile in a folder (e.g. for a contest).
     Profile(ProfileArgs),
 
     /// Setup workspace
@@ -1704,52 +3067,150 @@ enum Args {
 
 fn main() {
     let args = Args::parse();
-    let mut params = get_params();
+    let task = Task::load();
+    if let Some(profile) = &task.profile {
+        // these commands are needed to fix a missing profile, so they don't fail because of it
+        let fixes_settings = matches!(args, Args::Profile(_) | Args::Config(_) | Args::Setup(_));
+        let exists = read_settings().is_none_or(|x| x.config.contains_key(profile));
+        if exists || !fixes_settings {
+            PROFILE_OVERRIDE.set(profile.clone()).unwrap();
+        } else {
+            eprintln!(
+                "Warning: no profile \"{}\" from task.json, using the current one",
+                profile
+            );
+        }
+    }
+    let mut task = task.with_defaults(task_defaults());
     match args {
-        Args::Stress(args) => stress_test(args, &params),
-        Args::IStress(args) => stress_test_inline(args, &params),
-        Args::Test(args) => run_tests(args, &params),
-        Args::Interact(args) => interact(args, &params),
-        Args::Parse(args) => parse(args, &params),
-        Args::MakeFile(args) => make_file(args, &mut params),
-        Args::Init(args) => init_task(args, &mut params),
-        Args::MakeTest(args) => make_test(args, &params),
-        Args::Draw(args) => draw::draw(args, &params),
-        Args::SplitTest(args) => split_test(args, &params),
-        Args::Multirun(args) => multirun(args, &params),
-        Args::Approx(args) => approx::approx(args, &params),
-        Args::Config(args) => config(args, &params),
-        Args::Profile(args) => profile(args, &params),
-        Args::Workspace(args) => workspace(args, &params),
-        Args::Build(args) => build(args, &params),
+        Args::Stress(args) => stress_test(args, &task),
+        Args::IStress(args) => stress_test_inline(args, &task),
+        Args::Test(args) => run_tests(args, &task),
+        Args::Interact(args) => interact(args, &task),
+        Args::Parse(args) => parse(args, &mut task),
+        Args::MakeFile(args) => make_file(args, &mut task),
+        Args::Init(args) => init_task(args, &mut task),
+        Args::MakeTest(args) => make_test(args, &task),
+        Args::Draw(args) => draw::draw(args, &task),
+        Args::SplitTest(args) => split_test(args, &task),
+        Args::FetchTests(args) => fetch_tests(args, &mut task),
+        Args::Multirun(args) => multirun(args, &task),
+        Args::Approx(args) => approx::approx(args, &task),
+        Args::Submit(args) => submit::submit(args, &task),
+        Args::Bundle(args) => bundle::bundle(args, &task),
+        Args::Lib(args) => library::lib(args, &task),
+        Args::Status(args) => submit::status(args, &task),
+        Args::Contest(args) => contest(args, &task),
+        Args::Config(args) => config(args, &task),
+        Args::Setup(args) => setup(args, &task),
+        Args::Profile(args) => profile(args, &task),
+        Args::Workspace(args) => workspace(args, &task),
+        Args::Build(args) => build(args, &task),
 
         Args::BuildDebug(args) => build(
             BuildArgs {
                 profile: "debug".to_string(),
                 filename: args.filename,
             },
-            &params,
+            &task,
         ),
         Args::BuildRelease(args) => build(
             BuildArgs {
                 profile: "release".to_string(),
                 filename: args.filename,
             },
-            &params,
+            &task,
         ),
         Args::BuildGdb(args) => build(
             BuildArgs {
                 profile: "gdb".to_string(),
                 filename: args.filename,
             },
-            &params,
+            &task,
         ),
     };
 }
 
 // *********************************** internal ***********************************
 
+/// Runs the main solution with memory_limit in megabytes. If the problem uses files for
+/// input or output ("input_file", "output_file" of the task), runs it in SANDBOX_DIR with
+/// the test copied to the input file, and then copies the output file to fout
+fn run_main(filename: &str, fin: &str, fout: &str, timeout: f64, memory_limit: Option<u64>, task: &Task) -> ExitStatus {
+    let input_file = task.input_file.as_ref();
+    let output_file = task.output_file.as_ref();
+    if input_file.is_none() && output_file.is_none() {
+        return run_and_wait_limited(&[filename], fin, fout, Some(timeout), memory_limit, None);
+    }
+
+    let sandbox = Path::new(SANDBOX_DIR);
+    if sandbox.exists() {
+        fs::remove_dir_all(sandbox).unwrap();
+    }
+    fs::create_dir_all(sandbox).unwrap();
+    if let Some(input_file) = input_file {
+        fs::copy(fin, sandbox.join(input_file)).unwrap();
+    }
+
+    // files from the current directory have to be found from the sandbox as well,
+    // their absolute paths are kept as single arguments, as they may contain spaces
+    let current_dir = std::env::current_dir().unwrap();
+    let args = filename
+        .split(' ')
+        .map(|part| {
+            let exe_part = format!("{}.exe", part);
+            if Path::new(part).is_file() {
+                current_dir.join(part).to_str().unwrap().to_string()
+            } else if cfg!(windows) && Path::new(&exe_part).is_file() {
+                current_dir.join(exe_part).to_str().unwrap().to_string()
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let result = run_args_limited(args, fin, fout, Some(timeout), memory_limit, Some(sandbox));
+
+    if let Some(output_file) = output_file {
+        if let Ok(output) = fs::read(sandbox.join(output_file)) {
+            fs::write(fout, output).unwrap();
+        }
+    }
+    result
+}
+
 fn run_and_wait(filename: &[&str], fin: &str, fout: &str, timeout: Option<f64>) -> ExitStatus {
+    run_and_wait_limited(filename, fin, fout, timeout, None, None)
+}
+
+/// Same as run_and_wait, but also limits virtual memory to memory_limit megabytes (only on unix)
+/// and runs the process in cwd if specified
+fn run_and_wait_limited(
+    filename: &[&str],
+    fin: &str,
+    fout: &str,
+    timeout: Option<f64>,
+    memory_limit: Option<u64>,
+    cwd: Option<&Path>,
+) -> ExitStatus {
+    let mut filename_vec: Vec<String> = Vec::new();
+    for &item in filename.iter() {
+        filename_vec.extend(item.split(" ").map(String::from).collect::<Vec<_>>());
+    }
+
+    fix_unix_filename_vec(&mut filename_vec);
+    run_args_limited(filename_vec, fin, fout, timeout, memory_limit, cwd)
+}
+
+/// Same as run_and_wait_limited, but with the program and its arguments already split
+fn run_args_limited(
+    mut filename_vec: Vec<String>,
+    fin: &str,
+    fout: &str,
+    timeout: Option<f64>,
+    memory_limit: Option<u64>,
+    cwd: Option<&Path>,
+) -> ExitStatus {
     let stdin = match fin {
         "" => Redirection::Pipe,
         name => Redirection::File(fs::File::open(name).unwrap()),
@@ -1759,19 +3220,24 @@ fn run_and_wait(filename: &[&str], fin: &str, fout: &str, timeout: Option<f64>)
         name => Redirection::File(fs::File::create(name).unwrap()),
     };
 
-    let mut filename_vec: Vec<String> = Vec::new();
-    for &item in filename.iter() {
-        filename_vec.extend(item.split(" ").map(|x| String::from(x)).collect::<Vec<_>>());
+    if let Some(memory_limit) = memory_limit.filter(|_| cfg!(unix)) {
+        let mut wrapped_vec = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            format!("ulimit -v {} && exec \"$@\"", memory_limit * 1024),
+            "sh".to_string(),
+        ];
+        wrapped_vec.extend(filename_vec);
+        filename_vec = wrapped_vec;
     }
 
-    fix_unix_filename_vec(&mut filename_vec);
-
     let mut p = match Popen::create(
         &filename_vec[..],
         PopenConfig {
             stdin: stdin,
             stdout: stdout,
             stderr: Redirection::File(fs::File::create("err").unwrap()),
+            cwd: cwd.map(|x| x.as_os_str().to_owned()),
             ..Default::default()
         },
     ) {
@@ -1863,60 +3329,25 @@ fn get_available_tests() -> Vec<i32> {
     v.iter().map(|x| x[2..].parse().unwrap()).collect()
 }
 
-fn get_params() -> HashMap<String, String> {
-    let mut outer_params = read_params(&["../", LOCAL_PARAMS_NAME].concat());
-    let inner_params = read_params(LOCAL_PARAMS_NAME);
-    if outer_params.contains_key("url") && !inner_params.contains_key("url") {
-        let mut url = outer_params.get("url").unwrap().clone();
-        if url.as_bytes().last().unwrap() != &b'/' {
-            url.push('/');
-        }
-        let folder = std::env::current_dir()
-            .unwrap()
-            .as_path()
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        url += &folder;
-        outer_params.insert("url".to_string(), url);
-    }
-    if !outer_params.contains_key("url") {
-        if let Some(url) = guess_url_from_path() {
-            outer_params.insert("url".to_string(), url);
-        }
-    }
-    outer_params.extend(inner_params);
-    outer_params
-}
-
-fn read_params(filename: &str) -> HashMap<String, String> {
-    if !Path::new(filename).exists() {
-        return HashMap::new();
-    }
-    let mut res = HashMap::new();
-    let lines = read_lines_trim(filename);
-    for item in lines.iter() {
-        let idx = item.find(':').unwrap();
-        res.insert(item[..idx].to_string(), item[idx + 1..].to_string());
+/// Url from the task, or guessed from the path of the current folder.
+/// Only for commands that need it, so that others work in any folder
+fn get_url(task: &Task) -> Result<String, String> {
+    if let Some(url) = &task.url {
+        return Ok(url.clone());
     }
-    res
+    guess_url_from_path().map_err(|e| format!("no \"url\" in {} and {}", task::TASK_FILE, e))
 }
 
-fn write_params(filename: &str, params: &mut HashMap<String, String>) {
-    let mut file = fs::File::create(filename).unwrap();
-    for (key, value) in params.iter() {
-        file.write(&format!("{}:{}\n", key, value).as_bytes()).unwrap();
+fn write_tests(tests: &[(String, String)]) {
+    for (input, answer) in tests.iter() {
+        let index = first_available_test();
+        fs::write(format!("in{}", index), input).unwrap();
+        if !answer.is_empty() {
+            fs::write(format!("ans{}", index), answer).unwrap();
+        }
     }
 }
 
-fn add_param(key: &str, value: &str, params: &mut HashMap<String, String>) {
-    params.insert(key.to_string(), value.to_string());
-
-    write_params("params", params);
-}
-
 fn first_available_test() -> i32 {
     let mut tests = get_available_tests();
     tests.sort();
@@ -1928,79 +3359,22 @@ fn first_available_test() -> i32 {
     -1
 }
 
-fn get_problem_source() -> ProblemSource {
-    let path = std::env::current_dir().unwrap().to_str().unwrap().to_string();
-    let parts: Vec<&str>;
-    if path.contains("\\") {
-        parts = path.split("\\").collect();
-    } else {
-        parts = path.split("/").collect();
-    }
-    if path.to_lowercase().contains("codeforces") {
-        let mut contest = match parts[parts.len() - 2].parse::<i32>() {
-            Ok(x) => x,
-            Err(_) => -1,
-        };
-        let mut problem = parts[parts.len() - 1].to_string();
-        if contest == -1 {
-            contest = 0;
-            let mut still_contest = true;
-            problem.clear();
-            for &c in parts[parts.len() - 1].as_bytes() {
-                if b'0' <= c && c <= b'9' && still_contest {
-                    contest = contest * 10 + (c - b'0') as i32;
-                } else {
-                    still_contest = false;
-                    problem.push(c as char);
-                }
-            }
-        }
-        if !problem.is_empty() && contest > 0 {
-            return ProblemSource::Codeforces(contest.to_string(), problem);
-        }
-    } else if path.to_lowercase().contains("codechef") {
-        let contest = parts[parts.len() - 2];
-        let problem = parts[parts.len() - 1];
-        return ProblemSource::CodeChef(contest.to_string(), problem.to_string());
-    } else if path.to_lowercase().contains("atcoder") {
-        let contest = parts[parts.len() - 2];
-        let problem = parts[parts.len() - 1];
-        return ProblemSource::AtCoder(contest.to_string(), problem.to_string());
-    } else if path.to_lowercase().contains("codingame") && path.to_lowercase().contains("puzzles") {
-        let problem = parts[parts.len() - 1];
-        return ProblemSource::CodinGamePuzzle(problem.to_string());
-    } else if path.to_lowercase().contains("cses") && path.to_lowercase().contains("problemset") {
-        let problem = parts[parts.len() - 1];
-        return ProblemSource::Cses(problem.to_string());
-    }
-    ProblemSource::None
-}
-
-fn guess_url_from_path() -> Option<String> {
-    let problem_source = get_problem_source();
-    if let ProblemSource::Codeforces(contest, problem) = problem_source {
-        if contest.parse::<i32>().unwrap() < 100000 {
-            return Some(format!(
-                "https://codeforces.com/contest/{}/problem/{}",
-                contest, problem
-            ));
-        } else {
-            return Some(format!("https://codeforces.com/gym/{}/problem/{}", contest, problem));
-        }
-    } else if let ProblemSource::CodeChef(contest, problem) = problem_source {
-        return Some(format!("https://www.codechef.com/{}/problems/{}", contest, problem));
-    } else if let ProblemSource::AtCoder(contest, problem) = problem_source {
-        return Some(format!(
-            "https://atcoder.jp/contests/{0}/tasks/{0}_{1}",
-            contest,
-            problem.to_lowercase()
-        ));
-    } else if let ProblemSource::CodinGamePuzzle(problem) = problem_source {
-        return Some(format!("https://www.codingame.com/ide/puzzle/{}", problem));
-    } else if let ProblemSource::Cses(problem) = problem_source {
-        return Some(format!("https://cses.fi/problemset/task/{}", problem));
-    }
-    None
+/// Problem sources from settings, if there are any, and built-in ones
+fn get_sources() -> Vec<Source> {
+    let custom = read_settings().map(|x| x.sources).unwrap_or_default();
+    source::all_sources(&custom)
+}
+
+fn guess_url_from_path() -> Result<String, String> {
+    let path = match std::env::current_dir() {
+        Ok(x) => x,
+        Err(e) => return Err(format!("can't get current folder, {}", e)),
+    };
+    let path = match path.to_str() {
+        Some(x) => x.to_string(),
+        None => return Err(format!("path {:?} is not valid unicode", path)),
+    };
+    source::guess_url(&path, &get_sources())
 }
 
 fn get_default_file_extension() -> String {
@@ -2058,7 +3432,9 @@ fn run_interactive(
             if line.is_empty() {
                 continue;
             }
-            txout.send(line).unwrap();
+            if txout.send(line).is_err() {
+                break;
+            }
         }
     });
     thread::spawn(move || {
@@ -2072,7 +3448,9 @@ fn run_interactive(
             if line.is_empty() {
                 continue;
             }
-            txerr.send(line).unwrap();
+            if txerr.send(line).is_err() {
+                break;
+            }
         }
     });
 
@@ -2080,12 +3458,19 @@ fn run_interactive(
 }
 
 fn compile_cpr_tmp_file() -> Result<(), ()> {
+    compile_cpp("cpr_tmp_file", "-DHOUSE")
+}
+
+/// Compiles "[name].cpp" into "[name]"
+fn compile_cpp(name: &str, extra_flags: &str) -> Result<(), ()> {
     print!("Compiling...");
     io::stdout().flush().unwrap();
 
     let mut p = Popen::create(
         &format!(
-            "g++ --std=c++20 -O2 cpr_tmp_file.cpp -o cpr_tmp_file -DHOUSE -Winvalid-pch {}",
+            "g++ --std=c++20 -O2 {0}.cpp -o {0} {1} -Winvalid-pch {2}",
+            name,
+            extra_flags,
             if cfg!(unix) { "" } else { "-Wl,-stack,1073741824" },
         )
         .split_whitespace()
@@ -2107,13 +3492,26 @@ fn compile_cpr_tmp_file() -> Result<(), ()> {
     Ok(())
 }
 
-fn get_templates_path() -> std::path::PathBuf {
-    let mut exe_path = std::env::current_exe().unwrap();
-    exe_path.pop();
-    exe_path.pop();
-    exe_path.pop();
-    exe_path.push("templates");
-    exe_path
+/// Folder with user templates: CPR_TEMPLATES, "templates_path" from config
+/// or "templates" next to settings.json
+fn get_templates_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(TEMPLATES_ENV).filter(|x| !x.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    if let Some(path) = read_settings().and_then(|x| x.config.get(x.active_profile())?.templates_path.clone()) {
+        return Some(PathBuf::from(path));
+    }
+    let path = settings_file().parent()?.join("templates");
+    if path.is_dir() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Template from the user templates folder, or the built-in one
+fn read_template(name: &str) -> Option<String> {
+    template::read(get_templates_path().as_deref(), name)
 }
 
 fn init_rust_directory() {
@@ -2125,7 +3523,7 @@ fn init_rust_directory() {
         .expect("You need to set \"libs_path\": \"/path/to/rlib\" in settings");
     std::fs::create_dir_all("src/bin").unwrap();
     if !std::path::Path::new("Cargo.toml").exists() {
-        let lines = fs::read_to_string(get_templates_path().join("Cargo.toml")).unwrap();
+        let lines = read_template("Cargo.toml").unwrap();
         let mut file = fs::File::create("Cargo.toml").unwrap();
         let current_dir = std::env::current_dir()
             .unwrap()
@@ -2147,27 +3545,8 @@ fn init_rust_directory() {
                     &[&rust_libs_path, "/rlib"].concat(),
                     &[&rust_libs_path, "/external"].concat(),
                 ] {
-                    let mut libs: Vec<(String, String)> = Vec::new();
-                    for path in fs::read_dir(folder).unwrap() {
-                        let path = path.unwrap().path();
-                        if !path.is_dir() {
-                            continue;
-                        }
-                        if path.join("Cargo.toml").exists() {
-                            let lines = fs::read_to_string(path.join("Cargo.toml")).unwrap();
-                            if let Some(name) = lines.trim().split('\n').find(|line| line.starts_with("name")) {
-                                let name = name.split('=').skip(1).next().unwrap().trim();
-                                libs.push((
-                                    name[1..name.len() - 1].to_string(),
-                                    path.file_name().unwrap().to_str().unwrap().to_string(),
-                                ));
-                            }
-                        }
-                    }
-                    libs.sort();
-
-                    for (name, path) in libs.into_iter() {
-                        file.write(format!("{} = {{ \"path\" = \"{}/{}\" }}\n", name, folder, path).as_bytes())
+                    for (name, path) in library::rust_crates(Path::new(folder)) {
+                        file.write_all((library::dependency_line(&name, &path) + "\n").as_bytes())
                             .unwrap();
                     }
                     file.write(&[b'\n']).unwrap();
@@ -2206,30 +3585,149 @@ fn init_rust_directory() {
         }
     }
     if !std::path::Path::new("rustfmt.toml").exists() {
-        fs::File::create("rustfmt.toml")
-            .unwrap()
-            .write(
-                &fs::read_to_string(get_templates_path().join("rustfmt.toml"))
-                    .unwrap()
-                    .as_bytes(),
-            )
-            .unwrap();
+        fs::write("rustfmt.toml", read_template("rustfmt.toml").unwrap()).unwrap();
     }
 }
 
-fn get_settings() -> Settings {
-    match serde_json::from_str::<Settings>(
-        &fs::read_to_string(SETTINGS_FILE).expect(&format!("No settings file found at {SETTINGS_FILE}")),
-    ) {
-        Ok(x) => {
-            if !x.config.contains_key(&x.profile) {
-                panic!("No config for profile {}", x.profile);
-            }
-            x
+/// Path to settings.json: CPR_CONFIG if it's set, otherwise "cpr/settings.json" in
+/// XDG_CONFIG_HOME ("~/.config" by default) or APPDATA on windows
+fn settings_file() -> PathBuf {
+    if let Some(path) = std::env::var_os(SETTINGS_ENV).filter(|x| !x.is_empty()) {
+        return PathBuf::from(path);
+    }
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+    };
+    config_dir
+        .unwrap_or(PathBuf::from("."))
+        .join("cpr")
+        .join("settings.json")
+}
+
+fn write_settings(settings: &Settings) {
+    let path = settings_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    fs::write(&path, serde_json::to_string_pretty(settings).unwrap()).unwrap();
+}
+
+/// Settings, if they exist and are valid. For things that are optional,
+/// so that commands without them still work
+fn read_settings() -> Option<Settings> {
+    let path = settings_file();
+    let data = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str::<Settings>(&data) {
+        Ok(x) => Some(x),
+        Err(e) => {
+            // settings are read several times by a command, but one warning is enough
+            static WARNING: std::sync::Once = std::sync::Once::new();
+            WARNING.call_once(|| eprintln!("Warning: can't parse json from {}: {}", path.display(), e));
+            None
         }
+    }
+}
+
+/// "task" from the config of the current profile, the root of task.json inheritance
+fn task_defaults() -> Task {
+    read_settings()
+        .and_then(|x| x.config.get(x.active_profile()).cloned())
+        .and_then(|x| x.task)
+        .unwrap_or_default()
+}
+
+fn get_settings() -> Settings {
+    let settings = load_settings();
+    if !settings.config.contains_key(settings.active_profile()) {
+        eprintln!(
+            "No config for profile \"{}\", run \"cpr setup\" to create it",
+            settings.active_profile()
+        );
+        std::process::exit(1);
+    }
+    settings
+}
+
+/// Same as get_settings, but the active profile may have no config, to fix it with "cpr profile"
+fn load_settings() -> Settings {
+    let path = settings_file();
+    let data = match fs::read_to_string(&path) {
+        Ok(x) => x,
         Err(_) => {
-            eprintln!("Can't parse json from \"settings.json\"");
+            eprintln!(
+                "No settings file found at {}, run \"cpr setup\" to create it",
+                path.display()
+            );
+            std::process::exit(1);
+        }
+    };
+    match serde_json::from_str::<Settings>(&data) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Can't parse json from {}: {}", path.display(), e);
             std::process::exit(1);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn companion_problem_names() {
+        let name = |url: &str, name: &str| companion_problem_name(&json!({ "url": url, "name": name }));
+        assert_eq!(
+            name("https://codeforces.com/contest/1234/problem/B1", "B1. Title"),
+            Some("B1".to_string())
+        );
+        assert_eq!(
+            name("https://atcoder.jp/contests/abc300/tasks/abc300_c", "C - Title"),
+            Some("abc300_c".to_string())
+        );
+        assert_eq!(name("https://judge.com/problem/17", "D. Title"), Some("D".to_string()));
+        assert_eq!(name("https://judge.com/problem/17", "E - Title"), Some("E".to_string()));
+        assert_eq!(name("https://judge.com/problem/17", "Long title"), None);
+    }
+
+    #[test]
+    fn config_keys() {
+        assert_eq!(split_config_key("templates_path"), ["templates_path"]);
+        assert_eq!(split_config_key("vars.x"), ["vars", "x"]);
+        assert_eq!(split_config_key("vars.a\\.b.c"), ["vars", "a.b", "c"]);
+        assert_eq!(split_config_key(".vscode.x"), [".vscode", "x"]);
+
+        let mut lines = Vec::new();
+        flatten_config(&json!({ "vars": { "a.b": 1, ".c": "x", "n": null } }), "", &mut lines);
+        assert_eq!(lines, ["vars..c = \"x\"", "vars.a\\.b = 1"]);
+        assert_eq!(split_config_key("vars..c"), ["vars", ".c"]);
+    }
+
+    #[test]
+    fn set_nested_config_value() {
+        let mut value = json!({ "vars": 1 });
+        set_config_value(&mut value, &split_config_key("vars.a\\.b"), json!("x"));
+        set_config_value(&mut value, &split_config_key("build.cpp"), json!(["g++"]));
+        assert_eq!(value, json!({ "vars": { "a.b": "x" }, "build": { "cpp": ["g++"] } }));
+    }
+
+    #[test]
+    fn interactive_template_var() {
+        let template = "[[if interactive]]\nflush\n[[else]]\nfast io\n[[end]]";
+        for (task, expected) in [
+            (json!({ "interactive": true }), "flush\n"),
+            (json!({ "interactive": false }), "fast io\n"),
+            (json!({}), "fast io\n"),
+        ] {
+            let task: Task = serde_json::from_value(task).unwrap();
+            let vars = template_vars(&task, "start", "cpp");
+            assert_eq!(template::render(template, &vars).unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A judge, that can be detected from the path of the problem folder.
+///
+/// Sources from "sources" in settings.json are checked before the built-in ones,
+/// a source with the same name replaces the built-in one.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Source {
+    pub name: String,
+    /// Substrings that all have to be in the lowercased path
+    pub path: Vec<String>,
+    /// Layouts of the last folders in the path, the first matching one is used.
+    /// "{contest}/{problem}" takes two last folders, "{contest:num}{problem}" splits
+    /// "1234A" into "1234" and "A". "{x:num}" matches only digits, "{x:num6}" at least 6 of them
+    pub layouts: Vec<String>,
+    /// Url template, e.g. "https://atcoder.jp/contests/{contest}/tasks/{contest}_{problem:lower}"
+    pub url: String,
+    /// Problem urls containing this are parsed with the "scraper"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Built-in scraper for samples and limits: "codeforces", "atcoder", "cses" or "codechef".
+    /// Competitive companion is used if there is none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scraper: Option<String>,
+}
+
+impl Source {
+    fn new(name: &str, path: &[&str], layouts: &[&str], url: &str, host: &str, scraper: Option<&str>) -> Self {
+        Source {
+            name: name.to_string(),
+            path: path.iter().map(|x| x.to_string()).collect(),
+            layouts: layouts.iter().map(|x| x.to_string()).collect(),
+            url: url.to_string(),
+            host: Some(host.to_string()),
+            scraper: scraper.map(|x| x.to_string()),
+        }
+    }
+
+    /// Url of the problem in folder "path", if the path belongs to this source
+    pub fn url_from_path(&self, path: &str) -> Option<String> {
+        let lower = path.to_lowercase();
+        if !self.path.iter().all(|x| lower.contains(&x.to_lowercase())) {
+            return None;
+        }
+        let folders: Vec<&str> = path.split(['/', '\\']).filter(|x| !x.is_empty()).collect();
+        self.layouts
+            .iter()
+            .find_map(|layout| match_layout(layout, &folders))
+            .map(|values| fill_template(&self.url, &values))
+    }
+
+    pub fn matches_url(&self, url: &str) -> bool {
+        self.host.as_ref().is_some_and(|host| url.contains(host.as_str()))
+    }
+}
+
+pub fn builtin_sources() -> Vec<Source> {
+    vec![
+        Source::new(
+            "codeforces-gym",
+            &["codeforces"],
+            &["{contest:num6}/{problem}", "{contest:num6}{problem}"],
+            "https://codeforces.com/gym/{contest}/problem/{problem}",
+            "codeforces.com",
+            Some("codeforces"),
+        ),
+        Source::new(
+            "codeforces",
+            &["codeforces"],
+            &["{contest:num}/{problem}", "{contest:num}{problem}"],
+            "https://codeforces.com/contest/{contest}/problem/{problem}",
+            "codeforces.com",
+            Some("codeforces"),
+        ),
+        Source::new(
+            "codechef",
+            &["codechef"],
+            &["{contest}/{problem}"],
+            "https://www.codechef.com/{contest}/problems/{problem}",
+            "codechef.com",
+            Some("codechef"),
+        ),
+        Source::new(
+            "atcoder",
+            &["atcoder"],
+            &["{contest}/{problem}"],
+            "https://atcoder.jp/contests/{contest}/tasks/{contest}_{problem:lower}",
+            "atcoder.jp",
+            Some("atcoder"),
+        ),
+        Source::new(
+            "codingame",
+            &["codingame", "puzzles"],
+            &["{problem}"],
+            "https://www.codingame.com/ide/puzzle/{problem}",
+            "codingame.com",
+            None,
+        ),
+        Source::new(
+            "cses",
+            &["cses", "problemset"],
+            &["{problem}"],
+            "https://cses.fi/problemset/task/{problem}",
+            "cses.fi",
+            Some("cses"),
+        ),
+        Source::new(
+            "kattis",
+            &["kattis"],
+            &["{problem}"],
+            "https://open.kattis.com/problems/{problem}",
+            "kattis.com",
+            None,
+        ),
+        Source::new(
+            "leetcode",
+            &["leetcode"],
+            &["{problem}"],
+            "https://leetcode.com/problems/{problem}/",
+            "leetcode.com",
+            None,
+        ),
+        Source::new(
+            "yandex",
+            &["yandex"],
+            &["{contest:num}/{problem}"],
+            "https://contest.yandex.ru/contest/{contest}/problems/{problem}/",
+            "contest.yandex",
+            None,
+        ),
+        Source::new(
+            "spoj",
+            &["spoj"],
+            &["{problem}"],
+            "https://www.spoj.com/problems/{problem:upper}/",
+            "spoj.com",
+            None,
+        ),
+        Source::new(
+            "luogu",
+            &["luogu"],
+            &["{problem}"],
+            "https://www.luogu.com.cn/problem/{problem:upper}",
+            "luogu.com",
+            None,
+        ),
+    ]
+}
+
+/// Sources from settings first, then built-in ones, that weren't overridden
+pub fn all_sources(custom: &[Source]) -> Vec<Source> {
+    let mut res = custom.to_vec();
+    res.extend(
+        builtin_sources()
+            .into_iter()
+            .filter(|x| !custom.iter().any(|y| y.name == x.name)),
+    );
+    res
+}
+
+/// Url of the problem in folder "path" from the first matching source,
+/// or the reason why it can't be derived
+pub fn guess_url(path: &str, sources: &[Source]) -> Result<String, String> {
+    let lower = path.to_lowercase();
+    let candidates: Vec<&Source> = sources
+        .iter()
+        .filter(|x| x.path.iter().all(|y| lower.contains(&y.to_lowercase())))
+        .collect();
+    if candidates.is_empty() {
+        return Err(format!(
+            "path \"{}\" doesn't belong to any of the known judges ({})",
+            path,
+            sources.iter().map(|x| x.name.as_str()).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if let Some(url) = candidates.iter().find_map(|x| x.url_from_path(path)) {
+        return Ok(url);
+    }
+    let layouts = candidates
+        .iter()
+        .map(|x| format!("{} ({})", x.name, x.layouts.join(", ")))
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(format!(
+        "last folders of \"{}\" don't match the layouts of {}",
+        path, layouts
+    ))
+}
+
+/// Matches last folders with layout like "{contest}/{problem}", returns values of placeholders
+fn match_layout(layout: &str, folders: &[&str]) -> Option<HashMap<String, String>> {
+    let patterns: Vec<&str> = layout.split('/').collect();
+    if patterns.len() > folders.len() {
+        return None;
+    }
+    let mut values = HashMap::new();
+    for (pattern, folder) in patterns.iter().zip(folders[folders.len() - patterns.len()..].iter()) {
+        match_folder(pattern, folder, &mut values)?;
+    }
+    Some(values)
+}
+
+fn match_folder(pattern: &str, folder: &str, values: &mut HashMap<String, String>) -> Option<()> {
+    let mut pattern = pattern;
+    let mut folder = folder;
+    while !pattern.is_empty() {
+        if !pattern.starts_with('{') {
+            let literal_len = pattern.find('{').unwrap_or(pattern.len());
+            folder = folder.strip_prefix(&pattern[..literal_len])?;
+            pattern = &pattern[literal_len..];
+            continue;
+        }
+        let end = pattern.find('}')?;
+        let (name, kind) = match pattern[1..end].split_once(':') {
+            Some((name, kind)) => (name, kind),
+            None => (&pattern[1..end], ""),
+        };
+        pattern = &pattern[end + 1..];
+
+        let len = if let Some(min_digits) = kind.strip_prefix("num") {
+            let len = folder.find(|c: char| !c.is_ascii_digit()).unwrap_or(folder.len());
+            if len < min_digits.parse::<usize>().unwrap_or(1) {
+                return None;
+            }
+            len
+        } else {
+            // takes everything up to the next literal
+            let next_literal = pattern.find('{').unwrap_or(pattern.len());
+            if next_literal == 0 {
+                folder.len()
+            } else {
+                folder.find(&pattern[..next_literal])?
+            }
+        };
+        if len == 0 {
+            return None;
+        }
+        values.insert(name.to_string(), folder[..len].to_string());
+        folder = &folder[len..];
+    }
+    if folder.is_empty() {
+        Some(())
+    } else {
+        None
+    }
+}
+
+/// Replaces "{name}", "{name:lower}" and "{name:upper}" in template
+fn fill_template(template: &str, values: &HashMap<String, String>) -> String {
+    let mut res = template.to_string();
+    for (name, value) in values.iter() {
+        res = res
+            .replace(&format!("{{{}}}", name), value)
+            .replace(&format!("{{{}:lower}}", name), &value.to_lowercase())
+            .replace(&format!("{{{}:upper}}", name), &value.to_uppercase());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder_values(pattern: &str, folder: &str) -> Option<Vec<(String, String)>> {
+        let mut values = HashMap::new();
+        match_folder(pattern, folder, &mut values)?;
+        let mut values: Vec<_> = values.into_iter().collect();
+        values.sort();
+        Some(values)
+    }
+
+    #[test]
+    fn match_folder_placeholders() {
+        let pair = |x: &str, y: &str| (x.to_string(), y.to_string());
+        assert_eq!(
+            folder_values("{contest:num}{problem}", "1234A1"),
+            Some(vec![pair("contest", "1234"), pair("problem", "A1")])
+        );
+        assert_eq!(
+            folder_values("{contest}_{problem}", "abc300_a"),
+            Some(vec![pair("contest", "abc300"), pair("problem", "a")])
+        );
+        assert_eq!(folder_values("{contest:num}{problem}", "A"), None);
+        assert_eq!(folder_values("{contest:num6}", "12345"), None);
+        assert_eq!(folder_values("{contest:num}", "123a"), None);
+    }
+
+    #[test]
+    fn guess_url_from_path() {
+        let sources = builtin_sources();
+        assert_eq!(
+            guess_url("/home/me/codeforces/1234/A", &sources).unwrap(),
+            "https://codeforces.com/contest/1234/problem/A"
+        );
+        assert_eq!(
+            guess_url("/home/me/Codeforces/1234A", &sources).unwrap(),
+            "https://codeforces.com/contest/1234/problem/A"
+        );
+        assert_eq!(
+            guess_url("/home/me/codeforces/104114/B", &sources).unwrap(),
+            "https://codeforces.com/gym/104114/problem/B"
+        );
+        assert_eq!(
+            guess_url("/home/me/atcoder/abc300/A", &sources).unwrap(),
+            "https://atcoder.jp/contests/abc300/tasks/abc300_a"
+        );
+        assert!(guess_url("/home/me/codeforces/round", &sources).is_err());
+        assert!(guess_url("/home/me/problems/a", &sources).is_err());
+    }
+
+    #[test]
+    fn custom_sources_replace_builtin() {
+        let custom = Source {
+            name: "codeforces".to_string(),
+            path: vec!["cf".to_string()],
+            layouts: vec!["{contest}/{problem}".to_string()],
+            url: "https://cf/{contest}/{problem:lower}".to_string(),
+            host: None,
+            scraper: None,
+        };
+        let sources = all_sources(&[custom]);
+        assert_eq!(sources.iter().filter(|x| x.name == "codeforces").count(), 1);
+        assert_eq!(guess_url("/cf/1234/A", &sources).unwrap(), "https://cf/1234/a");
+    }
+}
@@ -0,0 +1,628 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::{thread, time};
+
+use clap::Parser;
+use crossterm::cursor;
+use crossterm::terminal::{Clear, ClearType};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::blocking::Client;
+use serde_json::Value;
+use soup::prelude::*;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use crate::task::Task;
+use crate::{get_default_file_extension, get_settings, get_url, DEFAULT_FILE_NAME};
+
+const CODEFORCES_URL: &str = "https://codeforces.com";
+/// Polls of the verdict in a row, that can fail or not find the new submission, before giving up
+const MAX_POLL_ATTEMPTS: usize = 30;
+
+#[derive(Parser)]
+pub struct SubmitArgs {
+    /// Source file to submit, "main" of the task by default
+    filename: Option<String>,
+
+    /// Language id on the judge, overrides "submit_lang" from settings
+    #[arg(short, long)]
+    lang: Option<String>,
+
+    /// Don't wait for the verdict
+    #[arg(long)]
+    no_wait: bool,
+}
+
+/// Problem on codeforces, parsed from urls like "https://codeforces.com/contest/1234/problem/A",
+/// "https://codeforces.com/gym/101234/problem/A" or "https://codeforces.com/problemset/problem/1234/A"
+pub struct CodeforcesProblem {
+    /// "contest" or "gym"
+    pub kind: String,
+    pub contest: String,
+    pub index: String,
+}
+
+impl CodeforcesProblem {
+    pub fn from_url(url: &str) -> Option<Self> {
+        if !url.contains("codeforces.com") {
+            return None;
+        }
+        let parts = url.trim_end_matches('/').split('/').collect::<Vec<_>>();
+        let n = parts.len();
+        if n >= 4 && (parts[n - 4] == "contest" || parts[n - 4] == "gym") && parts[n - 2] == "problem" {
+            Some(CodeforcesProblem {
+                kind: parts[n - 4].to_string(),
+                contest: parts[n - 3].to_string(),
+                index: parts[n - 1].to_string(),
+            })
+        } else if n >= 4 && parts[n - 4] == "problemset" && parts[n - 3] == "problem" {
+            Some(CodeforcesProblem {
+                kind: "contest".to_string(),
+                contest: parts[n - 2].to_string(),
+                index: parts[n - 1].to_string(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+pub fn submit(args: SubmitArgs, task: &Task) {
+    let url = match get_url(task) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("No url for the problem: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let problem = match CodeforcesProblem::from_url(&url) {
+        Some(x) => x,
+        None => {
+            eprintln!("Submitting is supported only for codeforces, got url \"{}\"", url);
+            std::process::exit(1);
+        }
+    };
+
+    let filename = args.filename.or_else(|| task.main.clone()).unwrap_or_else(|| {
+        format!(
+            "{}.{}",
+            DEFAULT_FILE_NAME,
+            task.lang.clone().unwrap_or_else(get_default_file_extension)
+        )
+    });
+    let source = match fs::read_to_string(&filename) {
+        Ok(x) => x,
+        Err(_) => {
+            eprintln!("Can't read \"{}\"", filename);
+            std::process::exit(1);
+        }
+    };
+
+    let settings = get_settings();
+    let lang = args
+        .lang
+        .or_else(|| settings.config().submit_lang.get("codeforces").cloned())
+        .or_else(|| {
+            // the language of the submitted file, not of the profile
+            let extension = Path::new(&filename)
+                .extension()
+                .map(|x| x.to_string_lossy().to_string())
+                .or_else(|| task.lang.clone())
+                .unwrap_or_else(get_default_file_extension);
+            default_codeforces_lang(&extension).map(String::from)
+        });
+    let lang = match lang {
+        Some(x) => x,
+        None => {
+            eprintln!("Don't know language id, set \"submit_lang\": {{\"codeforces\": \"[id]\"}} in settings");
+            std::process::exit(1);
+        }
+    };
+
+    let auth = match settings.auth.get("codeforces") {
+        Some(x) => x,
+        None => {
+            eprintln!("Set \"auth\": {{\"codeforces\": {{\"login\": ..., \"password\": ...}}}} in settings");
+            std::process::exit(1);
+        }
+    };
+
+    let client = Client::builder().cookie_store(true).build().unwrap();
+
+    print!("Logging in...");
+    io::stdout().flush().unwrap();
+    let handle = match codeforces_login(&client, &auth.login, &auth.password) {
+        Some(x) => x,
+        None => {
+            eprintln!("\rCan't login to codeforces as \"{}\"", auth.login);
+            std::process::exit(1);
+        }
+    };
+
+    print!(
+        "\rSubmitting {} to {}{} as {}...",
+        filename, problem.contest, problem.index, handle
+    );
+    io::stdout().flush().unwrap();
+
+    // the verdict is waited for a submission newer than this one
+    let previous_id = if args.no_wait {
+        None
+    } else {
+        match last_codeforces_submission(&problem, &handle) {
+            Ok(x) => x.map(|x| x.id),
+            Err(e) => {
+                eprintln!(
+                    "\nCan't get submissions from codeforces: {}. Use --no-wait to submit without waiting for the verdict",
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let submit_url = format!("{}/{}/{}/submit", CODEFORCES_URL, problem.kind, problem.contest);
+    let submit_page = client.get(&submit_url).send().unwrap().text().unwrap();
+    let csrf_token = match codeforces_csrf_token(&submit_page) {
+        Some(x) => x,
+        None => {
+            eprintln!("\nCan't find csrf_token on the submit page");
+            std::process::exit(1);
+        }
+    };
+    let response = client
+        .post(format!("{}?csrf_token={}", submit_url, csrf_token))
+        .form(&[
+            ("csrf_token", csrf_token.as_str()),
+            ("ftaa", &random_string(18)),
+            ("bfaa", &random_string(32)),
+            ("action", "submitSolutionFormSubmitted"),
+            ("submittedProblemIndex", &problem.index),
+            ("programTypeId", &lang),
+            ("source", &source),
+            ("tabSize", "4"),
+            ("sourceFile", ""),
+            ("_tta", "176"),
+        ])
+        .send()
+        .unwrap();
+
+    // on success codeforces redirects to the list of submissions
+    if !response.url().path().ends_with("/my") {
+        let page = response.text().unwrap();
+        let error = Soup::new(&page)
+            .tag("span")
+            .class("error")
+            .find_all()
+            .map(|x| x.text())
+            .find(|x| !x.trim().is_empty())
+            .unwrap_or("unknown error".to_string());
+        eprintln!("\nSubmission failed: {}", error.trim());
+        std::process::exit(1);
+    }
+    println!(" done");
+
+    if args.no_wait {
+        return;
+    }
+
+    let mut last_line = String::new();
+    let mut attempts = 0;
+    loop {
+        match last_codeforces_submission(&problem, &handle) {
+            Ok(Some(submission)) if Some(submission.id) > previous_id => {
+                attempts = 0;
+                let finished = submission.finished;
+                let line = submission.to_string();
+                if line != last_line {
+                    print!("\r{:width$}\r", "", width = last_line.len());
+                    submission.print();
+                    last_line = line;
+                }
+                if finished {
+                    println!();
+                    break;
+                }
+            }
+            result => {
+                attempts += 1;
+                if attempts == MAX_POLL_ATTEMPTS {
+                    match result {
+                        Err(e) => eprintln!("\nCan't get the verdict from codeforces: {}", e),
+                        _ => eprintln!("\nThe submission doesn't appear in codeforces api, check it on the site"),
+                    }
+                    std::process::exit(1);
+                }
+            }
+        }
+        thread::sleep(time::Duration::from_millis(1000));
+    }
+}
+
+/// Logs in and returns handle of the user
+fn codeforces_login(client: &Client, login: &str, password: &str) -> Option<String> {
+    let enter_page = client
+        .get(format!("{}/enter", CODEFORCES_URL))
+        .send()
+        .ok()?
+        .text()
+        .ok()?;
+    let csrf_token = codeforces_csrf_token(&enter_page)?;
+
+    let response = client
+        .post(format!("{}/enter", CODEFORCES_URL))
+        .form(&[
+            ("csrf_token", csrf_token.as_str()),
+            ("action", "enter"),
+            ("ftaa", &random_string(18)),
+            ("bfaa", &random_string(32)),
+            ("handleOrEmail", login),
+            ("password", password),
+            ("_tta", "176"),
+            ("remember", "on"),
+        ])
+        .send()
+        .ok()?
+        .text()
+        .ok()?;
+
+    // header has links "/profile/[handle]" and "/[...]/logout" only for logged in users
+    let soup = Soup::new(&response);
+    let header = soup.tag("div").class("lang-chooser").find()?;
+    if !header
+        .tag("a")
+        .find_all()
+        .any(|a| a.get("href").is_some_and(|x| x.ends_with("/logout")))
+    {
+        return None;
+    }
+    let handle = header
+        .tag("a")
+        .find_all()
+        .filter_map(|a| a.get("href"))
+        .find_map(|href| href.strip_prefix("/profile/").map(String::from))
+        .unwrap_or(login.to_string());
+    Some(handle)
+}
+
+fn codeforces_csrf_token(page: &str) -> Option<String> {
+    let soup = Soup::new(page);
+    soup.tag("meta")
+        .attr("name", "X-Csrf-Token")
+        .find()
+        .and_then(|x| x.get("content"))
+        .or_else(|| {
+            soup.tag("input")
+                .attr("name", "csrf_token")
+                .find()
+                .and_then(|x| x.get("value"))
+        })
+}
+
+/// Default language ids for "programTypeId"
+fn default_codeforces_lang(extension: &str) -> Option<&'static str> {
+    match extension {
+        "cpp" => Some("89"),
+        "rs" => Some("75"),
+        "py" => Some("31"),
+        "kt" => Some("88"),
+        "java" => Some("87"),
+        _ => None,
+    }
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(len).collect()
+}
+
+pub struct Submission {
+    pub id: u64,
+    pub problem: String,
+    pub verdict: String,
+    pub finished: bool,
+    /// Failed test for rejected submissions, current test while testing
+    pub test: Option<u64>,
+    pub time: u64,
+    /// In kilobytes
+    pub memory: u64,
+}
+
+impl Submission {
+    pub fn from_codeforces(data: &Value) -> Option<Self> {
+        let verdict = data["verdict"].as_str().unwrap_or("IN_QUEUE").to_string();
+        let test = match verdict.as_str() {
+            "OK" | "IN_QUEUE" | "COMPILATION_ERROR" | "SKIPPED" => None,
+            _ => Some(data["passedTestCount"].as_u64().unwrap_or(0) + 1),
+        };
+        Some(Submission {
+            id: data["id"].as_u64()?,
+            problem: format!(
+                "{}{}",
+                data["problem"]["contestId"]
+                    .as_u64()
+                    .map(|x| x.to_string())
+                    .unwrap_or_default(),
+                data["problem"]["index"].as_str().unwrap_or("")
+            ),
+            finished: verdict != "IN_QUEUE" && verdict != "TESTING",
+            verdict,
+            test,
+            time: data["timeConsumedMillis"].as_u64().unwrap_or(0),
+            memory: data["memoryConsumedBytes"].as_u64().unwrap_or(0) / 1024,
+        })
+    }
+
+    fn verdict_text(&self) -> String {
+        match self.test {
+            Some(test) => format!("{} on test {}", self.verdict, test),
+            None => self.verdict.clone(),
+        }
+    }
+
+    /// Prints the submission in one line without line break
+    pub fn print(&self) {
+        let color = if !self.finished {
+            Color::Yellow
+        } else if self.verdict == "OK" || self.verdict == "AC" {
+            Color::Green
+        } else {
+            Color::Red
+        };
+
+        let mut stdout = StandardStream::stdout(ColorChoice::Always);
+        write!(&mut stdout, "#{} {}: ", self.id, self.problem).unwrap();
+        stdout.set_color(ColorSpec::new().set_fg(Some(color))).unwrap();
+        write!(&mut stdout, "{}", self.verdict_text()).unwrap();
+        stdout.set_color(&ColorSpec::new()).unwrap();
+        write!(&mut stdout, ", {} ms, {} KB", self.time, self.memory).unwrap();
+        stdout.flush().unwrap();
+    }
+}
+
+impl std::fmt::Display for Submission {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {}: {}, {} ms, {} KB",
+            self.id,
+            self.problem,
+            self.verdict_text(),
+            self.time,
+            self.memory
+        )
+    }
+}
+
+/// Submissions of the user for the problem, the latest first, or the error of the request
+pub fn codeforces_submissions(
+    problem: &CodeforcesProblem,
+    handle: &str,
+    count: usize,
+) -> Result<Vec<Submission>, String> {
+    let url = format!(
+        "{}/api/contest.status?contestId={}&handle={}&from=1&count={}",
+        CODEFORCES_URL,
+        problem.contest,
+        handle,
+        count.max(20)
+    );
+    let data = reqwest::blocking::get(&url)
+        .and_then(|x| x.json::<Value>())
+        .map_err(|e| e.to_string())?;
+    match data["result"].as_array() {
+        Some(result) => Ok(result
+            .iter()
+            .filter(|x| x["problem"]["index"].as_str() == Some(problem.index.as_str()))
+            .filter_map(Submission::from_codeforces)
+            .take(count)
+            .collect()),
+        None => Err(data["comment"].as_str().unwrap_or("unknown error").to_string()),
+    }
+}
+
+/// Submissions of the user for the problem among the latest 100 submissions of the user,
+/// the latest first, or the error of the request
+pub fn codeforces_recent_submissions(
+    problem: &CodeforcesProblem,
+    handle: &str,
+    count: usize,
+) -> Result<Vec<Submission>, String> {
+    let url = format!("{}/api/user.status?handle={}&from=1&count=100", CODEFORCES_URL, handle);
+    let data = reqwest::blocking::get(&url)
+        .and_then(|x| x.json::<Value>())
+        .map_err(|e| e.to_string())?;
+    match data["result"].as_array() {
+        Some(result) => Ok(result
+            .iter()
+            .filter(|x| {
+                x["problem"]["contestId"].as_u64().map(|x| x.to_string()).as_ref() == Some(&problem.contest)
+                    && x["problem"]["index"].as_str() == Some(problem.index.as_str())
+            })
+            .filter_map(Submission::from_codeforces)
+            .take(count)
+            .collect()),
+        None => Err(data["comment"].as_str().unwrap_or("unknown error").to_string()),
+    }
+}
+
+fn last_codeforces_submission(problem: &CodeforcesProblem, handle: &str) -> Result<Option<Submission>, String> {
+    codeforces_submissions(problem, handle, 1).map(|x| x.into_iter().next())
+}
+
+/// Contest and task from urls like "https://atcoder.jp/contests/abc123/tasks/abc123_c"
+pub fn atcoder_task(url: &str) -> Option<(String, String)> {
+    if !url.contains("atcoder.jp") {
+        return None;
+    }
+    let parts = url.trim_end_matches('/').split('/').collect::<Vec<_>>();
+    let n = parts.len();
+    if n >= 4 && parts[n - 4] == "contests" && parts[n - 2] == "tasks" {
+        Some((parts[n - 3].to_string(), parts[n - 1].to_string()))
+    } else {
+        None
+    }
+}
+
+/// Submissions of the user for the task from the public submissions page, the latest first,
+/// or the error of the request
+fn atcoder_submissions(contest: &str, task: &str, user: &str, count: usize) -> Result<Vec<Submission>, String> {
+    let url = format!(
+        "https://atcoder.jp/contests/{}/submissions?f.Task={}&f.User={}",
+        contest, task, user
+    );
+    let page = reqwest::blocking::get(&url)
+        .and_then(|x| x.error_for_status())
+        .and_then(|x| x.text())
+        .map_err(|e| e.to_string())?;
+    let soup = Soup::new(&page);
+    // there is no table without submissions
+    let table = match soup.tag("tbody").find() {
+        Some(x) => x,
+        None => return Ok(Vec::new()),
+    };
+
+    // columns are: time, task, user, language, score, code size, status, [exec time, memory,] detail
+    Ok(table
+        .tag("tr")
+        .find_all()
+        .filter_map(|row| {
+            let cells = row
+                .tag("td")
+                .find_all()
+                .map(|x| x.text().trim().to_string())
+                .collect::<Vec<_>>();
+            let id = row
+                .tag("a")
+                .find_all()
+                .filter_map(|a| a.get("href"))
+                .filter(|href| href.contains("/submissions/"))
+                .find_map(|href| href.rsplit('/').next().and_then(|x| x.parse::<u64>().ok()))?;
+            let verdict = cells.get(6)?.clone();
+            let number = |i: usize| {
+                cells
+                    .get(i)
+                    .and_then(|x| x.split_whitespace().next())
+                    .and_then(|x| x.parse::<u64>().ok())
+                    .unwrap_or(0)
+            };
+            let (time, memory) = if cells.len() >= 10 {
+                (number(7), number(8))
+            } else {
+                (0, 0)
+            };
+            Some(Submission {
+                id,
+                problem: task.to_string(),
+                finished: !verdict.contains("WJ") && !verdict.contains("WR") && !verdict.contains('/'),
+                verdict,
+                test: None,
+                time,
+                memory,
+            })
+        })
+        .take(count)
+        .collect())
+}
+
+#[derive(Parser)]
+pub struct StatusArgs {
+    /// Number of latest submissions to show
+    #[arg(short = 'n', long, default_value_t = 5)]
+    count: usize,
+
+    /// Handle on the judge, login from "auth" in settings by default
+    #[arg(long)]
+    handle: Option<String>,
+
+    /// Show submissions once, without waiting for final verdicts
+    #[arg(long)]
+    once: bool,
+}
+
+pub fn status(args: StatusArgs, task: &Task) {
+    let url = match get_url(task) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("No url for the problem: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let handle = |judge: &str| -> String {
+        match args
+            .handle
+            .clone()
+            .or_else(|| get_settings().auth.get(judge).map(|x| x.login.clone()))
+        {
+            Some(x) => x,
+            None => {
+                eprintln!("Specify --handle or set \"auth\": {{\"{}\": ...}} in settings", judge);
+                std::process::exit(1);
+            }
+        }
+    };
+    let count = args.count;
+
+    let fetch: Box<dyn Fn() -> Result<Vec<Submission>, String>> =
+        if let Some(problem) = CodeforcesProblem::from_url(&url) {
+            let handle = handle("codeforces");
+            Box::new(move || codeforces_recent_submissions(&problem, &handle, count))
+        } else if let Some((contest, task)) = atcoder_task(&url) {
+            let handle = handle("atcoder");
+            Box::new(move || atcoder_submissions(&contest, &task, &handle, count))
+        } else {
+            eprintln!(
+                "Status is supported only for codeforces and atcoder, got url \"{}\"",
+                url
+            );
+            std::process::exit(1);
+        };
+
+    let mut stdout = io::stdout();
+    let mut printed_lines: usize = 0;
+    let mut attempts = 0;
+    loop {
+        let submissions = match fetch() {
+            Ok(x) => x,
+            Err(e) => {
+                attempts += 1;
+                // a failure on the first request is most likely a wrong handle
+                if printed_lines == 0 || attempts == MAX_POLL_ATTEMPTS {
+                    eprintln!("Can't get submissions: {}", e);
+                    std::process::exit(1);
+                }
+                thread::sleep(time::Duration::from_millis(2000));
+                continue;
+            }
+        };
+        attempts = 0;
+
+        if printed_lines != 0 {
+            write!(stdout, "{}", cursor::MoveUp(printed_lines as u16)).unwrap();
+        }
+        let mut lines: usize = 0;
+        if submissions.is_empty() {
+            write!(stdout, "\r{}No submissions\n", Clear(ClearType::CurrentLine)).unwrap();
+            lines += 1;
+        }
+        for submission in submissions.iter() {
+            write!(stdout, "\r{}", Clear(ClearType::CurrentLine)).unwrap();
+            stdout.flush().unwrap();
+            submission.print();
+            writeln!(stdout).unwrap();
+            lines += 1;
+        }
+        while lines < printed_lines {
+            write!(stdout, "\r{}\n", Clear(ClearType::CurrentLine)).unwrap();
+            lines += 1;
+        }
+        stdout.flush().unwrap();
+        printed_lines = lines;
+
+        if args.once || submissions.iter().all(|x| x.finished) {
+            break;
+        }
+        thread::sleep(time::Duration::from_millis(2000));
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::BuildProfile;
+
+/// Problem info in the problem folder, filled by "cpr parse", "cpr mk" and "cpr fetch-tests"
+pub const TASK_FILE: &str = "task.json";
+
+/// Old "key:value" format, read if there is no TASK_FILE
+const LEGACY_PARAMS_FILE: &str = "params";
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Checker {
+    /// "check" is run on input and output merged into "inout", as with "cpr test --check"
+    Inout,
+    /// "check in out ans", see "cpr fetch-tests"
+    Testlib,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparator {
+    /// Lines are compared without trailing whitespace
+    Exact,
+    /// Numbers are compared with this absolute or relative error, as with "cpr test --eps"
+    Eps(f64),
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Task {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Source file with the solution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main: Option<String>,
+    /// Extension of the main file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// "single" or "multiNumber" from competitive companion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_type: Option<String>,
+    /// In milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<u64>,
+    /// In megabytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<String>,
+    /// Can be set to false in a folder inside an interactive one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interactive: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checker: Option<Checker>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparator: Option<Comparator>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Default timeout in seconds for programs without time limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<f64>,
+    /// Build profiles, override the ones from settings with the same name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub build: BTreeMap<String, BuildProfile>,
+    /// Profile from settings to use in this folder instead of the current one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Merged tasks of the parent folders, fields that are the same there are not saved
+    #[serde(skip)]
+    inherited: Option<Box<Task>>,
+}
+
+impl Task {
+    /// Task of the current folder. Fields, that are not set, are taken from the parent folders
+    /// up to the root. The url of a parent (e.g. of the contest) gets the names of the folders
+    /// below it appended. Broken task.json of a parent is skipped with a warning
+    pub fn load() -> Task {
+        let current_dir = std::env::current_dir().unwrap_or(PathBuf::from("."));
+        let mut dirs: Vec<&Path> = current_dir.ancestors().collect();
+        dirs.reverse();
+
+        let count = dirs.len();
+        let mut task = Task::default();
+        for (i, dir) in dirs.into_iter().enumerate() {
+            // legacy params are read only in the problem folder and its parent, as before task.json
+            let inner = match Task::read(dir, i + 2 >= count) {
+                Ok(x) => x.unwrap_or_default(),
+                Err(e) if i + 1 == count => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Warning: {}, skipping it", e);
+                    Task::default()
+                }
+            };
+            task.inherited = None;
+            if inner.url.is_none() {
+                let folder = dir.file_name().and_then(|x| x.to_str());
+                task.url = match (task.url.take(), folder) {
+                    (Some(mut url), Some(folder)) if !url.is_empty() => {
+                        if !url.ends_with('/') {
+                            url.push('/');
+                        }
+                        Some(url + folder)
+                    }
+                    _ => None,
+                };
+            }
+            let inherited = task.clone();
+            task = task.merge(inner);
+            task.inherited = Some(Box::new(inherited));
+        }
+        task
+    }
+
+    /// Fields, that are not set, are taken from defaults (e.g. from the profile config)
+    pub fn with_defaults(self, defaults: Task) -> Task {
+        let inherited = self.inherited.clone().map(|x| Box::new(defaults.clone().merge(*x)));
+        let mut task = defaults.merge(self);
+        task.inherited = inherited;
+        task
+    }
+
+    /// Reads TASK_FILE or, if "legacy", legacy params from the folder, None if there are neither
+    fn read(dir: &Path, legacy: bool) -> Result<Option<Task>, String> {
+        let path = dir.join(TASK_FILE);
+        if let Ok(data) = fs::read_to_string(&path) {
+            return serde_json::from_str(&data)
+                .map(Some)
+                .map_err(|e| format!("Can't parse {}: {}", path.display(), e));
+        }
+        match fs::read_to_string(dir.join(LEGACY_PARAMS_FILE)) {
+            Ok(data) if legacy => Ok(Some(Task::from_params(&data))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Parses the old format with a "key:value" on each line
+    fn from_params(data: &str) -> Task {
+        let mut task = Task::default();
+        for line in data.lines().map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            let (key, value) = match line.split_once(':') {
+                Some(x) => x,
+                None => {
+                    eprintln!("Skipping line \"{}\" in params, expected \"key:value\"", line);
+                    continue;
+                }
+            };
+            let value = value.to_string();
+            match key {
+                "name" => task.name = Some(value),
+                "group" => task.group = Some(value),
+                "url" => task.url = Some(value),
+                "main" => task.main = Some(value),
+                "lang" => task.lang = Some(value),
+                "test_type" => task.test_type = Some(value),
+                "time_limit" => task.time_limit = value.parse().ok(),
+                "memory_limit" => task.memory_limit = value.parse().ok(),
+                "input_file" => task.input_file = Some(value),
+                "output_file" => task.output_file = Some(value),
+                "interactive" => task.interactive = Some(value == "true"),
+                "checker" => task.checker = serde_json::from_value(serde_json::Value::String(value)).ok(),
+                _ => eprintln!("Skipping unknown key \"{}\" in params", key),
+            }
+        }
+        task
+    }
+
+    /// Fields of other override the ones of self
+    fn merge(self, other: Task) -> Task {
+        Task {
+            name: other.name.or(self.name),
+            group: other.group.or(self.group),
+            url: other.url.or(self.url),
+            main: other.main.or(self.main),
+            lang: other.lang.or(self.lang),
+            test_type: other.test_type.or(self.test_type),
+            time_limit: other.time_limit.or(self.time_limit),
+            memory_limit: other.memory_limit.or(self.memory_limit),
+            input_file: other.input_file.or(self.input_file),
+            output_file: other.output_file.or(self.output_file),
+            interactive: other.interactive.or(self.interactive),
+            checker: other.checker.or(self.checker),
+            comparator: other.comparator.or(self.comparator),
+            tags: if other.tags.is_empty() { self.tags } else { other.tags },
+            timeout: other.timeout.or(self.timeout),
+            build: {
+                let mut build = self.build;
+                build.extend(other.build);
+                build
+            },
+            profile: other.profile.or(self.profile),
+            inherited: None,
+        }
+    }
+
+    /// Fields of self, that are different from the ones of parent
+    fn without(&self, parent: &Task) -> Task {
+        fn own<T: Clone + PartialEq>(x: &Option<T>, parent: &Option<T>) -> Option<T> {
+            if x != parent {
+                x.clone()
+            } else {
+                None
+            }
+        }
+        Task {
+            name: own(&self.name, &parent.name),
+            group: own(&self.group, &parent.group),
+            url: own(&self.url, &parent.url),
+            main: own(&self.main, &parent.main),
+            lang: own(&self.lang, &parent.lang),
+            test_type: own(&self.test_type, &parent.test_type),
+            time_limit: own(&self.time_limit, &parent.time_limit),
+            memory_limit: own(&self.memory_limit, &parent.memory_limit),
+            input_file: own(&self.input_file, &parent.input_file),
+            output_file: own(&self.output_file, &parent.output_file),
+            interactive: own(&self.interactive, &parent.interactive),
+            checker: own(&self.checker, &parent.checker),
+            comparator: own(&self.comparator, &parent.comparator),
+            tags: if self.tags != parent.tags {
+                self.tags.clone()
+            } else {
+                Vec::new()
+            },
+            timeout: own(&self.timeout, &parent.timeout),
+            build: self
+                .build
+                .iter()
+                .filter(|(name, profile)| parent.build.get(*name) != Some(profile))
+                .map(|(name, profile)| (name.clone(), profile.clone()))
+                .collect(),
+            profile: own(&self.profile, &parent.profile),
+            inherited: None,
+        }
+    }
+
+    /// Writes the task to TASK_FILE in the current folder, without fields inherited from parents
+    pub fn save(&self) {
+        let task = match &self.inherited {
+            Some(parent) => self.without(parent),
+            None => self.clone(),
+        };
+        fs::write(TASK_FILE, serde_json::to_string_pretty(&task).unwrap() + "\n").unwrap();
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.interactive == Some(true)
+    }
+
+    /// Time limit in seconds
+    pub fn time_limit_secs(&self) -> Option<f64> {
+        self.time_limit.map(|x| x as f64 / 1000.)
+    }
+
+    /// Epsilon from the comparator
+    pub fn eps(&self) -> Option<f64> {
+        match self.comparator {
+            Some(Comparator::Eps(eps)) => Some(eps),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn to_json(task: &Task) -> serde_json::Value {
+        serde_json::to_value(task).unwrap()
+    }
+
+    #[test]
+    fn legacy_params() {
+        let task = Task::from_params("name:A. Sum\nurl:https://x.com/a:b\n\ntime_limit:2000\ninteractive:true\nbad\n");
+        assert_eq!(
+            to_json(&task),
+            json!({ "name": "A. Sum", "url": "https://x.com/a:b", "time_limit": 2000, "interactive": true })
+        );
+    }
+
+    #[test]
+    fn merge_and_without() {
+        let parent: Task =
+            serde_json::from_value(json!({ "group": "Round", "interactive": true, "tags": ["dp"] })).unwrap();
+        let child: Task = serde_json::from_value(json!({ "name": "A", "interactive": false })).unwrap();
+        let task = parent.clone().merge(child);
+        assert_eq!(
+            to_json(&task),
+            json!({ "name": "A", "group": "Round", "interactive": false, "tags": ["dp"] })
+        );
+        assert_eq!(task.interactive, Some(false));
+        // "interactive: false" differs from the parent, so it is kept
+        assert_eq!(
+            to_json(&task.without(&parent)),
+            json!({ "name": "A", "interactive": false })
+        );
+        assert_eq!(to_json(&parent.without(&parent)), json!({}));
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Templates embedded into the binary, files with the same names in the user templates folder
+/// override them
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("Cargo.toml", include_str!("../templates/Cargo.toml")),
+    (
+        "Cargo_workspace.toml",
+        include_str!("../templates/Cargo_workspace.toml"),
+    ),
+    (
+        "cpp_compile_flags.txt",
+        include_str!("../templates/cpp_compile_flags.txt"),
+    ),
+    ("rustfmt.toml", include_str!("../templates/rustfmt.toml")),
+    ("start/cpp.txt", include_str!("../templates/start/cpp.txt")),
+    ("start/kt.txt", include_str!("../templates/start/kt.txt")),
+    ("start/rs.txt", include_str!("../templates/start/rs.txt")),
+    (
+        "stress_test_check_template.cpp",
+        include_str!("../templates/stress_test_check_template.cpp"),
+    ),
+    (
+        "stress_test_template.cpp",
+        include_str!("../templates/stress_test_template.cpp"),
+    ),
+];
+
+/// Template "name" (e.g. "start/cpp.txt") from the user folder "dir", or the built-in one
+pub fn read(dir: Option<&Path>, name: &str) -> Option<String> {
+    if let Some(data) = dir.and_then(|dir| fs::read_to_string(dir.join(name)).ok()) {
+        return Some(data);
+    }
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, data)| data.to_string())
+}
+
+/// Contents of "start/*.txt" templates by extension, both from "dir" and built-in ones
+pub fn start_templates(dir: Option<&Path>) -> BTreeMap<String, String> {
+    let mut res = BTreeMap::new();
+    for (name, data) in BUILTIN_TEMPLATES.iter() {
+        if let Some(extension) = name.strip_prefix("start/").and_then(|x| x.strip_suffix(".txt")) {
+            res.insert(extension.to_string(), data.to_string());
+        }
+    }
+    if let Some(files) = dir.and_then(|dir| fs::read_dir(dir.join("start")).ok()) {
+        for file in files.filter_map(|x| x.ok()).map(|x| x.path()) {
+            if file.extension().is_some_and(|x| x == "txt") {
+                if let (Some(extension), Ok(data)) = (file.file_stem(), fs::read_to_string(&file)) {
+                    res.insert(extension.to_string_lossy().to_string(), data);
+                }
+            }
+        }
+    }
+    res
+}
+
+/// Template types used in a start template: "start" and the values of "type" in
+/// both "[[type:a|b]]" filters and "[[if type:a]]" conditions
+pub fn types(template: &str) -> Vec<String> {
+    let mut types = vec!["start".to_string()];
+    for (filter, _) in template.split("[[").skip(1).filter_map(|x| x.split_once("]]")) {
+        let filter = filter
+            .trim_start_matches("if ")
+            .trim_start_matches("elif ")
+            .trim_start_matches('!');
+        if let Some(values) = filter.strip_prefix("type:") {
+            for value in values.split('|') {
+                if !types.iter().any(|x| x == value) {
+                    types.push(value.to_string());
+                }
+            }
+        }
+    }
+    types
+}
+
+/// Renders a template for "cpr mk".
+///
+/// "[[name]]" is replaced with the value of variable "name", unknown names are left as is.
+/// A line starting with "[[name:a|b]]" is kept only if "name" is "a" or "b".
+/// Lines "[[if cond]]", "[[elif cond]]", "[[else]]" and "[[end]]" keep or remove blocks of lines
+/// and can be nested. "cond" is "name" (set and not empty), "name:a|b" or "!cond".
+pub fn render(template: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut res = String::new();
+    // for each open "if": whether the current branch is kept and whether some branch was kept
+    let mut stack: Vec<(bool, bool)> = Vec::new();
+    let active = |stack: &Vec<(bool, bool)>| stack.iter().all(|x| x.0);
+
+    for (i, line) in template.lines().enumerate() {
+        let directive = line.trim();
+        if let Some(cond) = directive_arg(directive, "if") {
+            let kept = active(&stack) && check(cond, vars);
+            stack.push((kept, kept));
+        } else if let Some(cond) = directive_arg(directive, "elif") {
+            let (_, taken) = stack.pop().ok_or(format!("line {}: \"elif\" without \"if\"", i + 1))?;
+            let kept = active(&stack) && !taken && check(cond, vars);
+            stack.push((kept, taken || kept));
+        } else if directive == "[[else]]" {
+            let (_, taken) = stack.pop().ok_or(format!("line {}: \"else\" without \"if\"", i + 1))?;
+            stack.push((active(&stack) && !taken, true));
+        } else if directive == "[[end]]" {
+            stack.pop().ok_or(format!("line {}: \"end\" without \"if\"", i + 1))?;
+        } else if active(&stack) {
+            if let Some(line) = filter_line(line, vars) {
+                res += &substitute(line, vars);
+                res.push('\n');
+            }
+        }
+    }
+    if !stack.is_empty() {
+        return Err(format!("{} \"if\" without \"end\"", stack.len()));
+    }
+    Ok(res)
+}
+
+/// "cond" from "[[name cond]]"
+fn directive_arg<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    line.strip_prefix("[[")?
+        .strip_suffix("]]")?
+        .strip_prefix(name)?
+        .strip_prefix(' ')
+        .map(|x| x.trim())
+}
+
+fn check(cond: &str, vars: &BTreeMap<String, String>) -> bool {
+    if let Some(cond) = cond.strip_prefix('!') {
+        return !check(cond, vars);
+    }
+    match cond.split_once(':') {
+        Some((name, values)) => vars
+            .get(name)
+            .is_some_and(|value| values.split('|').any(|x| x == value)),
+        None => vars.get(cond).is_some_and(|value| !value.is_empty()),
+    }
+}
+
+/// Removes "[[name:a|b]]" filters from the beginning of the line, None if some of them fail
+fn filter_line<'a>(line: &'a str, vars: &BTreeMap<String, String>) -> Option<&'a str> {
+    let mut line = line;
+    while let Some(rest) = line.strip_prefix("[[") {
+        let end = rest.find("]]")?;
+        if !rest[..end].contains(':') {
+            // a variable, not a filter
+            break;
+        }
+        if !check(&rest[..end], vars) {
+            return None;
+        }
+        line = &rest[end + 2..];
+    }
+    Some(line)
+}
+
+/// Replaces "[[name]]" of known variables in one pass. "[libs_path]" of old templates is
+/// replaced as well, other single brackets are left as is, as they are common in code
+fn substitute(line: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut res = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("[[") {
+        res += &rest[..start];
+        let inner = &rest[start + 2..];
+        match inner.find("]]").and_then(|end| Some((end, vars.get(&inner[..end])?))) {
+            Some((end, value)) => {
+                res += value;
+                rest = &inner[end + 2..];
+            }
+            None => {
+                res += "[[";
+                rest = inner;
+            }
+        }
+    }
+    res += rest;
+    match vars.get("libs_path") {
+        Some(path) => res.replace("[libs_path]", path),
+        None => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(x, y)| (x.to_string(), y.to_string())).collect()
+    }
+
+    #[test]
+    fn render_nested_conditions() {
+        let template = "\
+[[if type:tstart]]
+tests
+[[if !interactive]]
+samples
+[[end]]
+[[elif type:gstart]]
+gen
+[[else]]
+start
+[[end]]
+main";
+        let render = |pairs| render(template, &vars(pairs)).unwrap();
+        assert_eq!(render(&[("type", "tstart")]), "tests\nsamples\nmain\n");
+        assert_eq!(render(&[("type", "tstart"), ("interactive", "true")]), "tests\nmain\n");
+        assert_eq!(render(&[("type", "gstart")]), "gen\nmain\n");
+        assert_eq!(render(&[("type", "start")]), "start\nmain\n");
+    }
+
+    #[test]
+    fn render_filters_and_variables() {
+        let vars = vars(&[("type", "gcj"), ("author", "me"), ("libs_path", "/libs")]);
+        let template =
+            "[[type:tstart|gcj]]// [[author]]\n[[type:start]]skipped\n#include \"[libs_path]/a.h\"\na[i] = [[i]];";
+        assert_eq!(
+            render(template, &vars).unwrap(),
+            "// me\n#include \"/libs/a.h\"\na[i] = [[i]];\n"
+        );
+    }
+
+    #[test]
+    fn render_unbalanced() {
+        assert!(render("[[if author]]\nx", &BTreeMap::new()).is_err());
+        assert!(render("[[else]]", &BTreeMap::new()).is_err());
+        assert!(render("[[end]]", &BTreeMap::new()).is_err());
+    }
+}
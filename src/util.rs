@@ -1,5 +1,5 @@
 pub fn fix_unix_filename(filename: &str) -> String {
-    if cfg!(unix) && !filename.starts_with("./") {
+    if cfg!(unix) && !filename.starts_with("./") && !filename.starts_with('/') {
         ["./", filename].concat().to_string()
     } else {
         filename.to_string()